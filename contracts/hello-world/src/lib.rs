@@ -1,23 +1,570 @@
 #![no_std]
 
-use soroban_sdk::{contract, contractimpl, contracttype, Address, Env, String, Symbol, symbol_short};
+use soroban_sdk::{
+    contract, contracterror, contractimpl, contracttype, panic_with_error, symbol_short, token,
+    vec, xdr::ToXdr, Address, Bytes, BytesN, Env, Map, String, Symbol, TryFromVal, Val, Vec,
+};
+
+// Ledger counts used for TTL management (~5s per ledger)
+const DAY_IN_LEDGERS: u32 = 17280;
+const INSTANCE_BUMP_AMOUNT: u32 = 7 * DAY_IN_LEDGERS;
+const INSTANCE_LIFETIME_THRESHOLD: u32 = INSTANCE_BUMP_AMOUNT - DAY_IN_LEDGERS;
+const ORDER_BUMP_AMOUNT: u32 = 30 * DAY_IN_LEDGERS;
+const ORDER_LIFETIME_THRESHOLD: u32 = ORDER_BUMP_AMOUNT - DAY_IN_LEDGERS;
+
+// Maximum length of a seller's profile metadata, in bytes
+pub const MAX_SELLER_METADATA_LEN: u32 = 256;
+
+// Maximum number of orders returned by one page of a listing
+// Each order costs three ledger reads (index slot, order and its queue node),
+// keeping a page well under the per-transaction read entry limit
+pub const MAX_PAGE_SIZE: u32 = 10;
+
+// Maximum number of checkpoints in an order's shipment timeline
+// The whole timeline is one ledger entry, this keeps it small
+pub const MAX_TIMELINE_LEN: u32 = 32;
+
+// Range of the star rating a buyer may give an order
+pub const MIN_RATING: u32 = 1;
+pub const MAX_RATING: u32 = 5;
+
+// Maximum number of orders in one create_orders or fulfill_orders batch
+// Keeps a full batch within the per-transaction ledger entry and CPU limits,
+// see test_batch_size_fits_network_limits
+pub const MAX_BATCH_SIZE: u32 = 6;
+
+// Version of the stored Order and Config layouts, bumped whenever their fields change
+pub const SCHEMA_VERSION: u32 = 2;
+
+// Version of the contract code, bumped once for every change that gets deployed
+pub const CODE_VERSION: u32 = 2;
+
+// Version of the OrderEvent payload, bumped whenever its fields change
+pub const EVENT_VERSION: u32 = 1;
+
+// Basis points making up a whole escrow, used to split it on a dispute ruling and
+// to take the platform fee
+pub const MAX_BPS: u32 = 10_000;
+
+// Highest platform fee the admin may set, in basis points (10%)
+pub const MAX_FEE_BPS: u32 = 1_000;
+
+// Errors returned by the contract entry points, codes are stable
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum OrderError {
+    NotFound = 1,
+    AlreadyFulfilled = 2,
+    Unauthorized = 3,
+    InvalidState = 4,
+    Paused = 5,
+    DeadlineExpired = 6,
+    ProductTooLong = 7,
+    InvalidAmount = 8,
+    SellerNotFound = 9,
+    SellerNotApproved = 10,
+    MetadataTooLong = 11,
+    EmptyOrder = 12,
+    TooManyItems = 13,
+    InvalidQuantity = 14,
+    Overflow = 15,
+    InvalidLimit = 16,
+    InvalidCursor = 17,
+    InvalidDeliveryCode = 18,
+    InvalidAttestation = 19,
+    AttestationReplayed = 20,
+    InvalidDeadline = 21,
+    DeadlineNotReached = 22,
+    DisputeWindowClosed = 23,
+    InvalidRuling = 24,
+    InvalidLineIndex = 25,
+    TimelineFull = 26,
+    InvalidRating = 27,
+    AlreadyRated = 28,
+    BatchTooLarge = 29,
+    InvalidFee = 30,
+    InsufficientFees = 31,
+    InsufficientFunds = 32,
+}
+
+// Lifecycle of an order
+//
+// Created -> Accepted -> Shipped -> Delivered -> Completed
+// Created, Accepted and Shipped may be delivered directly, in several parcels going
+// through PartiallyFulfilled, or refunded once their deadline has passed (as may a
+// PartiallyFulfilled order), Created may be cancelled by the buyer, Created and Accepted
+// may be rejected by the seller (both ending Cancelled), and a Delivered order may
+// be disputed, after which an arbiter's ruling completes or refunds it
+#[contracttype]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+pub enum OrderStatus {
+    Created,
+    Accepted,
+    Shipped,
+    // Some, but not all, units of the order have been delivered
+    PartiallyFulfilled,
+    Delivered,
+    Completed,
+    Cancelled,
+    Disputed,
+    Refunded,
+}
+
+impl OrderStatus {
+    // Whether an order may move from this status to `next`
+    pub fn can_transition_to(self, next: OrderStatus) -> bool {
+        use OrderStatus::*;
+        matches!(
+            (self, next),
+            (Created, Accepted)
+                | (Accepted, Shipped)
+                | (Created | Accepted | Shipped, PartiallyFulfilled)
+                | (Created | Accepted | Shipped | PartiallyFulfilled, Delivered)
+                | (Delivered | Disputed, Completed)
+                | (Created | Accepted, Cancelled)
+                | (Delivered, Disputed)
+                | (
+                    Created | Accepted | Shipped | PartiallyFulfilled | Disputed,
+                    Refunded
+                )
+        )
+    }
+}
+
+// One SKU of an order
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LineItem {
+    pub sku: String,
+    pub quantity: u32,
+    // Price of a single unit, in the order's token
+    pub unit_price: i128,
+}
 
 // Structure to represent an order
 #[contracttype]
-#[derive(Clone)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Order {
     pub order_id: u64,
     pub buyer: Address,
-    pub product: String,
-    pub is_fulfilled: bool,
+    // Counterparty who owes fulfillment and receives the escrow
+    pub seller: Address,
+    pub items: Vec<LineItem>,
+    // Token contract and order total (sum of quantity * unit_price), held in escrow
+    pub token: Address,
+    pub amount: i128,
+    // Units delivered so far of each line item, by position in `items`
+    pub fulfilled: Vec<u32>,
+    // Part of the escrow already paid to the seller for partially fulfilled items
+    pub released: i128,
+    // sha256 of the secret delivery code kept by the buyer, if the order uses one
+    // (32 bytes, held as Bytes since Option<BytesN> fields can't be converted in tests)
+    pub delivery_hash: Option<Bytes>,
+    // ed25519 key of the courier/oracle whose attestation proved delivery, if any
+    // (32 bytes, held as Bytes for the same reason)
+    pub attester: Option<Bytes>,
+    pub status: OrderStatus,
+    // Ledger timestamp at which the order entered each status it went through
+    pub status_times: Map<OrderStatus, u64>,
+    pub timestamp: u64,
+    // Ledger timestamp after which the order can no longer be fulfilled and the
+    // buyer may claim a refund, pushed back by the time fulfillment spends paused
+    pub deadline: u64,
+    // Ledger timestamp of the last change to the order
+    pub updated_at: u64,
+    // Platform fee in basis points taken from payments to the seller, fixed when
+    // the order is created
+    pub fee_bps: u32,
+    // Total paused time of the fulfillment scope as of the order's last status
+    // change; pauses since then extend its deadline and dispute window
+    pub paused_offset: u64,
+    // Position of the order in its buyer's index, unknown for orders created
+    // before it was recorded
+    pub buyer_slot: Option<u32>,
+}
+
+// Order layout of schema version 1, before `updated_at` and `fee_bps` were added
+// Orders written back then are stored as this bare struct rather than in a StoredOrder
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OrderV1 {
+    pub order_id: u64,
+    pub buyer: Address,
+    pub seller: Address,
+    pub items: Vec<LineItem>,
+    pub token: Address,
+    pub amount: i128,
+    pub fulfilled: Vec<u32>,
+    pub released: i128,
+    pub delivery_hash: Option<Bytes>,
+    pub attester: Option<Bytes>,
+    pub status: OrderStatus,
+    pub status_times: Map<OrderStatus, u64>,
+    pub timestamp: u64,
+    pub deadline: u64,
+}
+
+impl OrderV1 {
+    // Convert to the latest layout, taking the last status change as the last update
+    // and without a platform fee, as none was agreed on when the order was placed
+    pub fn upgrade(self) -> Order {
+        let updated_at = self
+            .status_times
+            .values()
+            .iter()
+            .max()
+            .unwrap_or(self.timestamp);
+        Order {
+            order_id: self.order_id,
+            buyer: self.buyer,
+            seller: self.seller,
+            items: self.items,
+            token: self.token,
+            amount: self.amount,
+            fulfilled: self.fulfilled,
+            released: self.released,
+            delivery_hash: self.delivery_hash,
+            attester: self.attester,
+            status: self.status,
+            status_times: self.status_times,
+            timestamp: self.timestamp,
+            deadline: self.deadline,
+            updated_at,
+            fee_bps: 0,
+            paused_offset: 0,
+            buyer_slot: None,
+        }
+    }
+}
+
+// Versioned representation of an order entry, tagged with its schema version
+// New fields go in a new variant; older variants are upgraded when read. Schema
+// version 1 predates it, those orders are bare OrderV1 structs
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum StoredOrder {
+    V2(Order),
+}
+
+// Delivery statement signed off-chain by a registered attester key
+// The signature covers the XDR encoding of this struct
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DeliveryAttestation {
+    // Verifier contract the statement is addressed to, so that it cannot be
+    // replayed on another deployment trusting the same attester
+    pub contract: Address,
+    pub order_id: u64,
+    // ed25519 public key of the signing attester
+    pub attester: BytesN<32>,
+    // When the attester observed the delivery
+    pub timestamp: u64,
+    // Hash of the carrier's tracking data
+    pub tracking_hash: BytesN<32>,
+    // Single-use value per attester, guards against replay
+    pub nonce: u64,
+}
+
+// Outcome of a dispute, decided by an arbiter
+#[contracttype]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Ruling {
+    // Not ruled on yet
+    Pending,
+    // Pay the whole escrow to the seller
+    ReleaseToSeller,
+    // Return the whole escrow to the buyer
+    RefundBuyer,
+    // Pay this share of the escrow, in basis points, to the seller and the rest to the buyer
+    Split(u32),
+}
+
+// Dispute opened by the buyer over a delivered order
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Dispute {
+    // Hash of the buyer's off-chain statement of the problem
+    pub reason_hash: BytesN<32>,
+    pub opened_at: u64,
+    pub ruling: Ruling,
+    // Arbiter who ruled on the dispute and when, once resolved
+    pub arbiter: Option<Address>,
+    pub resolved_at: Option<u64>,
+}
+
+// Logistics checkpoint in an order's shipment timeline
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Checkpoint {
+    // Carrier-defined status (picked, in transit, at hub, out for delivery...)
+    pub status_code: u32,
+    pub timestamp: u64,
+    // Fulfiller or courier who recorded the checkpoint
+    pub actor: Address,
+    // Hash of the location, kept off-chain
+    pub location_hash: BytesN<32>,
+}
+
+// Outcome of one entry of a batch call
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BatchResult {
+    // Id of the order created or fulfilled
+    Ok(u64),
+    // Code of the OrderError the entry failed with
+    Err(u32),
+}
+
+// Buyer's rating of a fulfilled order
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Review {
+    // From MIN_RATING to MAX_RATING stars
+    pub rating: u32,
+    // Hash of the review text, kept off-chain
+    pub content_hash: BytesN<32>,
+    pub timestamp: u64,
+}
+
+// Aggregate of the ratings a seller received, the average is sum / count
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SellerRating {
+    pub sum: u64,
+    pub count: u32,
+}
+
+// Fulfillment track record of a seller
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SellerReputation {
+    // Orders delivered
+    pub fulfilled: u32,
+    // Orders the seller rejected or let pass their deadline undelivered
+    pub cancelled: u32,
+    pub disputed: u32,
+    // Disputes where the arbiter did not release the whole escrow to the seller
+    pub disputes_lost: u32,
+    // Seconds from creation to delivery, summed over and averaged across the
+    // fulfilled orders
+    pub total_fulfill_time: u64,
+    pub avg_fulfill_time: u64,
+}
+
+// Data payload of every ("order", <action>, order_id, buyer) event
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OrderEvent {
+    pub version: u32,
+    // Address that triggered the state change
+    pub actor: Address,
+    pub timestamp: u64,
+}
+
+// Data payload of the ("order", "resolved", order_id, buyer) event
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DisputeEvent {
+    pub version: u32,
+    pub arbiter: Address,
+    pub ruling: Ruling,
+    // How the escrow was paid out
+    pub seller_amount: i128,
+    pub buyer_amount: i128,
+    pub timestamp: u64,
+}
+
+// Data payload of the ("order", "rejected", order_id, buyer) event
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RejectionEvent {
+    pub version: u32,
+    pub seller: Address,
+    // Seller-defined code explaining the rejection (out of stock, can't ship there...)
+    pub reason_code: u32,
     pub timestamp: u64,
 }
 
+// Standing of a seller in the registry
+#[contracttype]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SellerStatus {
+    // Registered, waiting for admin approval
+    Pending,
+    Approved,
+    Suspended,
+}
+
+// Seller registry entry
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SellerProfile {
+    // Free-form profile data (name, contact, URL of a richer profile...)
+    pub metadata: String,
+    pub status: SellerStatus,
+    pub registered_at: u64,
+}
+
+// First and last order of a (seller, status) queue
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct QueueEnds {
+    pub head: u64,
+    pub tail: u64,
+}
+
+// Links of an order within its current (seller, status) queue
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct QueueNode {
+    pub prev: Option<u64>,
+    pub next: Option<u64>,
+}
+
+// Versions reported by the version view
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ContractVersion {
+    pub schema: u32,
+    pub code: u32,
+}
+
+// Contract-wide settings, managed by the admin
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Config {
+    // Seconds after creation within which an order must be fulfilled, unless the
+    // order was created with its own deadline
+    pub fulfillment_deadline: u64,
+    // Maximum length of a line item's SKU (or product name), in bytes
+    pub max_product_len: u32,
+    // Maximum number of line items in one order
+    pub max_items: u32,
+    // Maximum quantity of a single line item
+    pub max_quantity: u32,
+    // Seconds after delivery during which the buyer may open a dispute, after
+    // which anyone may complete the order
+    pub dispute_window: u64,
+    // Platform fee kept from payments released to sellers, in basis points (at
+    // most MAX_FEE_BPS); each order keeps the rate in force when it was created
+    pub fee_bps: u32,
+}
+
+// Config layout of schema version 1, before pausing got its own scopes and the
+// platform fee was added
+// Configs written back then are stored as this bare struct rather than in a StoredConfig
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConfigV1 {
+    pub fulfillment_deadline: u64,
+    pub max_product_len: u32,
+    pub max_items: u32,
+    pub max_quantity: u32,
+    pub dispute_window: u64,
+    // When set, orders can neither be created nor change status
+    pub paused: bool,
+}
+
+impl ConfigV1 {
+    // Convert to the latest layout, without a platform fee
+    // The paused flag is carried over to the pause state separately
+    pub fn upgrade(self) -> Config {
+        Config {
+            fulfillment_deadline: self.fulfillment_deadline,
+            max_product_len: self.max_product_len,
+            max_items: self.max_items,
+            max_quantity: self.max_quantity,
+            dispute_window: self.dispute_window,
+            fee_bps: 0,
+        }
+    }
+}
+
+// Versioned representation of the config entry, tagged with its schema version
+// Schema version 1 predates it, that config is a bare ConfigV1 struct
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum StoredConfig {
+    V2(Config),
+}
+
+// Part of the order flow that can be paused in an emergency
+#[contracttype]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PauseScope {
+    // Creating new orders and registering sellers
+    Creation,
+    // Every change to existing orders: acceptance, shipping, fulfillment,
+    // settlement, cancellation, disputes, tracking and ratings
+    Fulfillment,
+    // Both of the above
+    All,
+}
+
+impl PauseScope {
+    // Whether pausing this scope pauses creation
+    pub fn covers_creation(self) -> bool {
+        matches!(self, PauseScope::Creation | PauseScope::All)
+    }
+
+    // Whether pausing this scope pauses changes to existing orders
+    pub fn covers_fulfillment(self) -> bool {
+        matches!(self, PauseScope::Fulfillment | PauseScope::All)
+    }
+}
+
+// Which scopes are currently paused
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PauseState {
+    pub creation: bool,
+    pub fulfillment: bool,
+}
+
+// Time the fulfillment scope has spent paused, which doesn't count against order
+// deadlines and dispute windows
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PauseClock {
+    // Seconds of pauses already lifted
+    pub total: u64,
+    // Ledger timestamp at which the current pause began, while fulfillment is paused
+    pub since: u64,
+}
+
 // Enum for storage keys
+// Admin, Config, Paused, PauseClock and OrderCount live in instance storage, orders and roles in their own persistent entries
 #[contracttype]
 pub enum OrderKey {
+    Admin,
+    Config,
+    Paused,
+    PauseClock,
     Order(u64),
     OrderCount,
+    Fulfiller(Address),
+    Seller(Address),
+    // Per-buyer index: number of orders and the order id at each position
+    BuyerOrderCount(Address),
+    BuyerOrder(Address, u32),
+    // Per-seller, per-status FIFO queues, kept as doubly linked lists of order ids
+    SellerQueue(Address, OrderStatus),
+    QueueNode(u64),
+    // Registered delivery attester keys and the nonces each has consumed
+    Attester(BytesN<32>),
+    AttesterNonce(BytesN<32>, u64),
+    Arbiter(Address),
+    Dispute(u64),
+    Courier(Address),
+    // Shipment timeline of an order, a Vec<Checkpoint> of at most MAX_TIMELINE_LEN
+    Timeline(u64),
+    Review(u64),
+    SellerRating(Address),
+    Reputation(Address),
+    Guardian(Address),
+    // Platform fees collected and not yet withdrawn, per token
+    FeeBalance(Address),
 }
 
 #[contract]
@@ -25,39 +572,1695 @@ pub struct OrderFulfillmentVerifier;
 
 #[contractimpl]
 impl OrderFulfillmentVerifier {
-    // Create a new order
-    pub fn create_order(env: Env, buyer: Address, product: String) -> u64 {
-        let mut count: u64 = env.storage().instance().get(&OrderKey::OrderCount).unwrap_or(0);
-        count += 1;
-
-        let new_order = Order {
-            order_id: count,
-            buyer,
-            product,
-            is_fulfilled: false,
+    // Set the admin allowed to manage the contract and the initial config
+    pub fn __constructor(env: Env, admin: Address, config: Config) {
+        if let Err(err) = validate_config(&config) {
+            panic_with_error!(&env, err);
+        }
+        env.storage().instance().set(&OrderKey::Admin, &admin);
+        write_config(&env, &config);
+    }
+
+    // Replace the contract config (admin only)
+    pub fn update_config(env: Env, config: Config) -> Result<(), OrderError> {
+        require_admin(&env);
+        bump_instance(&env);
+        validate_config(&config)?;
+
+        // Carry over a paused flag still held by a legacy config before replacing it
+        read_config(&env);
+        write_config(&env, &config);
+        Ok(())
+    }
+
+    // View the contract config
+    pub fn get_config(env: Env) -> Config {
+        bump_instance(&env);
+        read_config(&env)
+    }
+
+    // Replace the contract code with an uploaded Wasm, keeping its id and storage (admin only)
+    pub fn upgrade(env: Env, new_wasm_hash: BytesN<32>) -> Result<(), OrderError> {
+        let admin = require_admin(&env);
+        bump_instance(&env);
+
+        env.deployer()
+            .update_current_contract_wasm(new_wasm_hash.clone());
+        env.events()
+            .publish((symbol_short!("upgraded"), admin), new_wasm_hash);
+        Ok(())
+    }
+
+    // View the storage schema and code versions of the running contract
+    pub fn version(env: Env) -> ContractVersion {
+        bump_instance(&env);
+        ContractVersion {
+            schema: SCHEMA_VERSION,
+            code: CODE_VERSION,
+        }
+    }
+
+    // View the platform fees collected in a token and not yet withdrawn
+    pub fn fee_balance(env: Env, token: Address) -> i128 {
+        bump_instance(&env);
+        read_fee_balance(&env, &token)
+    }
+
+    // Pay out collected platform fees in a token (admin only)
+    pub fn withdraw_fees(
+        env: Env,
+        token: Address,
+        to: Address,
+        amount: i128,
+    ) -> Result<(), OrderError> {
+        let admin = require_admin(&env);
+        bump_instance(&env);
+        if amount <= 0 {
+            return Err(OrderError::InvalidAmount);
+        }
+        let balance = read_fee_balance(&env, &token);
+        if amount > balance {
+            return Err(OrderError::InsufficientFees);
+        }
+
+        write_fee_balance(&env, &token, balance - amount);
+        token::Client::new(&env, &token).transfer(&env.current_contract_address(), &to, &amount);
+        env.events()
+            .publish((symbol_short!("fees"), admin, token), (to, amount));
+        Ok(())
+    }
+
+    // Stop part of the order flow in an emergency (requires the signature of the
+    // admin or a guardian). Views keep working while paused
+    pub fn pause(env: Env, caller: Address, scope: PauseScope) -> Result<(), OrderError> {
+        caller.require_auth();
+        bump_instance(&env);
+        let admin: Address = env.storage().instance().get(&OrderKey::Admin).unwrap();
+        if caller != admin && !has_role(&env, &OrderKey::Guardian(caller.clone())) {
+            return Err(OrderError::Unauthorized);
+        }
+
+        set_paused(&env, &caller, scope, true);
+        Ok(())
+    }
+
+    // Resume part of the order flow (admin only, guardians can only pause)
+    pub fn unpause(env: Env, scope: PauseScope) -> Result<(), OrderError> {
+        let admin = require_admin(&env);
+        bump_instance(&env);
+
+        set_paused(&env, &admin, scope, false);
+        Ok(())
+    }
+
+    // View which parts of the order flow are paused
+    pub fn paused(env: Env) -> PauseState {
+        bump_instance(&env);
+        read_pause_state(&env)
+    }
+
+    // Grant the guardian role, allowing to pause the contract (admin only)
+    pub fn add_guardian(env: Env, guardian: Address) -> Result<(), OrderError> {
+        require_admin(&env);
+        bump_instance(&env);
+
+        let key = OrderKey::Guardian(guardian);
+        env.storage().persistent().set(&key, &true);
+        bump_persistent(&env, &key);
+        Ok(())
+    }
+
+    // Revoke the guardian role (admin only)
+    pub fn remove_guardian(env: Env, guardian: Address) -> Result<(), OrderError> {
+        require_admin(&env);
+        bump_instance(&env);
+
+        env.storage()
+            .persistent()
+            .remove(&OrderKey::Guardian(guardian));
+        Ok(())
+    }
+
+    // Check whether an address holds the guardian role
+    pub fn is_guardian(env: Env, address: Address) -> bool {
+        bump_instance(&env);
+        has_role(&env, &OrderKey::Guardian(address))
+    }
+
+    // Grant the fulfiller role (admin only)
+    pub fn add_fulfiller(env: Env, fulfiller: Address) -> Result<(), OrderError> {
+        require_admin(&env);
+        bump_instance(&env);
+
+        let key = OrderKey::Fulfiller(fulfiller);
+        env.storage().persistent().set(&key, &true);
+        bump_persistent(&env, &key);
+        Ok(())
+    }
+
+    // Revoke the fulfiller role (admin only)
+    pub fn remove_fulfiller(env: Env, fulfiller: Address) -> Result<(), OrderError> {
+        require_admin(&env);
+        bump_instance(&env);
+
+        env.storage()
+            .persistent()
+            .remove(&OrderKey::Fulfiller(fulfiller));
+        Ok(())
+    }
+
+    // Check whether an address holds the fulfiller role
+    pub fn is_fulfiller(env: Env, address: Address) -> bool {
+        bump_instance(&env);
+        has_role(&env, &OrderKey::Fulfiller(address))
+    }
+
+    // Appoint an arbiter who may resolve disputes (admin only)
+    pub fn add_arbiter(env: Env, arbiter: Address) -> Result<(), OrderError> {
+        require_admin(&env);
+        bump_instance(&env);
+
+        let key = OrderKey::Arbiter(arbiter);
+        env.storage().persistent().set(&key, &true);
+        bump_persistent(&env, &key);
+        Ok(())
+    }
+
+    // Dismiss an arbiter (admin only)
+    pub fn remove_arbiter(env: Env, arbiter: Address) -> Result<(), OrderError> {
+        require_admin(&env);
+        bump_instance(&env);
+
+        env.storage()
+            .persistent()
+            .remove(&OrderKey::Arbiter(arbiter));
+        Ok(())
+    }
+
+    // Check whether an address is an appointed arbiter
+    pub fn is_arbiter(env: Env, address: Address) -> bool {
+        bump_instance(&env);
+        has_role(&env, &OrderKey::Arbiter(address))
+    }
+
+    // Grant the courier role, allowing to record shipment checkpoints (admin only)
+    pub fn add_courier(env: Env, courier: Address) -> Result<(), OrderError> {
+        require_admin(&env);
+        bump_instance(&env);
+
+        let key = OrderKey::Courier(courier);
+        env.storage().persistent().set(&key, &true);
+        bump_persistent(&env, &key);
+        Ok(())
+    }
+
+    // Revoke the courier role (admin only)
+    pub fn remove_courier(env: Env, courier: Address) -> Result<(), OrderError> {
+        require_admin(&env);
+        bump_instance(&env);
+
+        env.storage()
+            .persistent()
+            .remove(&OrderKey::Courier(courier));
+        Ok(())
+    }
+
+    // Check whether an address holds the courier role
+    pub fn is_courier(env: Env, address: Address) -> bool {
+        bump_instance(&env);
+        has_role(&env, &OrderKey::Courier(address))
+    }
+
+    // Register as a seller, or update the profile of an existing registration
+    // New sellers start out Pending until approved by the admin
+    pub fn register_seller(env: Env, seller: Address, metadata: String) -> Result<(), OrderError> {
+        seller.require_auth();
+        bump_instance(&env);
+        ensure_not_paused(&env, PauseScope::Creation)?;
+
+        if metadata.len() > MAX_SELLER_METADATA_LEN {
+            return Err(OrderError::MetadataTooLong);
+        }
+
+        let profile = match read_seller(&env, &seller) {
+            Some(existing) => SellerProfile {
+                metadata,
+                ..existing
+            },
+            None => SellerProfile {
+                metadata,
+                status: SellerStatus::Pending,
+                registered_at: env.ledger().timestamp(),
+            },
+        };
+        write_seller(&env, &seller, &profile);
+        Ok(())
+    }
+
+    // Allow a registered seller to receive orders (admin only)
+    pub fn approve_seller(env: Env, seller: Address) -> Result<(), OrderError> {
+        set_seller_status(&env, &seller, SellerStatus::Approved)
+    }
+
+    // Stop a seller from receiving new orders (admin only)
+    pub fn suspend_seller(env: Env, seller: Address) -> Result<(), OrderError> {
+        set_seller_status(&env, &seller, SellerStatus::Suspended)
+    }
+
+    // View a seller's registry entry
+    pub fn get_seller(env: Env, seller: Address) -> Result<SellerProfile, OrderError> {
+        bump_instance(&env);
+        read_seller(&env, &seller).ok_or(OrderError::SellerNotFound)
+    }
+
+    // Create a single-product order and lock its payment in escrow (requires the buyer's signature)
+    // Kept for existing integrations, it is recorded as one line item of quantity 1
+    pub fn create_order(
+        env: Env,
+        buyer: Address,
+        seller: Address,
+        product: String,
+        token: Address,
+        amount: i128,
+    ) -> Result<u64, OrderError> {
+        buyer.require_auth();
+        bump_instance(&env);
+
+        let item = LineItem {
+            sku: product,
+            quantity: 1,
+            unit_price: amount,
+        };
+        create(&env, buyer, seller, vec![&env, item], token, None, None)
+    }
+
+    // Create an order for several line items and lock its total in escrow
+    // (requires the buyer's signature). The seller must be registered and approved
+    // When `delivery_hash` is set, fulfillment requires the code it commits to
+    // `deadline` overrides the configured fulfillment deadline with a ledger timestamp
+    pub fn create_order_with_items(
+        env: Env,
+        buyer: Address,
+        seller: Address,
+        items: Vec<LineItem>,
+        token: Address,
+        delivery_hash: Option<BytesN<32>>,
+        deadline: Option<u64>,
+    ) -> Result<u64, OrderError> {
+        buyer.require_auth();
+        bump_instance(&env);
+
+        create(&env, buyer, seller, items, token, delivery_hash, deadline)
+    }
+
+    // Create one single-product order per (product, amount) pair for the same
+    // seller and token, checking the buyer's signature once
+    // A failing entry is reported in its slot of the result and doesn't stop the rest
+    pub fn create_orders(
+        env: Env,
+        buyer: Address,
+        seller: Address,
+        products: Vec<(String, i128)>,
+        token: Address,
+    ) -> Result<Vec<BatchResult>, OrderError> {
+        buyer.require_auth();
+        bump_instance(&env);
+        ensure_not_paused(&env, PauseScope::Creation)?;
+        if products.len() > MAX_BATCH_SIZE {
+            return Err(OrderError::BatchTooLarge);
+        }
+
+        let mut results = Vec::new(&env);
+        for (product, amount) in products.iter() {
+            let item = LineItem {
+                sku: product,
+                quantity: 1,
+                unit_price: amount,
+            };
+            let result = create(
+                &env,
+                buyer.clone(),
+                seller.clone(),
+                vec![&env, item],
+                token.clone(),
+                None,
+                None,
+            );
+            results.push_back(match result {
+                Ok(order_id) => BatchResult::Ok(order_id),
+                Err(err) => BatchResult::Err(err as u32),
+            });
+        }
+        Ok(results)
+    }
+
+    // Accept a newly created order for fulfillment (requires the seller's signature)
+    pub fn accept_order(env: Env, order_id: u64) -> Result<(), OrderError> {
+        bump_instance(&env);
+        ensure_not_paused(&env, PauseScope::Fulfillment)?;
+
+        let mut order = load_order(&env, order_id)?;
+        order.seller.require_auth();
+        advance(&env, &mut order, OrderStatus::Accepted)?;
+        publish_order_event(&env, symbol_short!("accepted"), &order, &order.seller);
+        Ok(())
+    }
+
+    // Hand an accepted order over to shipping (requires a fulfiller's signature)
+    pub fn ship_order(env: Env, fulfiller: Address, order_id: u64) -> Result<(), OrderError> {
+        require_fulfiller(&env, &fulfiller)?;
+        ensure_not_paused(&env, PauseScope::Fulfillment)?;
+
+        let mut order = load_order(&env, order_id)?;
+        advance(&env, &mut order, OrderStatus::Shipped)?;
+        publish_order_event(&env, symbol_short!("shipped"), &order, &fulfiller);
+        Ok(())
+    }
+
+    // Mark order as fulfilled, i.e. delivered (requires a fulfiller's signature)
+    // Orders created with a delivery hash need the buyer's code as proof of delivery
+    pub fn fulfill_order(
+        env: Env,
+        fulfiller: Address,
+        order_id: u64,
+        delivery_code: Option<Bytes>,
+    ) -> Result<(), OrderError> {
+        require_fulfiller(&env, &fulfiller)?;
+        ensure_not_paused(&env, PauseScope::Fulfillment)?;
+
+        let mut order = load_order(&env, order_id)?;
+        check_deliverable(&env, &order)?;
+        verify_delivery_code(&env, &order, delivery_code)?;
+
+        deliver(&env, &mut order, &fulfiller)
+    }
+
+    // Mark several orders as fulfilled, checking the fulfiller's signature once
+    // Orders that can't be fulfilled (already fulfilled, expired, needing a delivery
+    // code...) are reported in their slot of the result and don't stop the rest
+    pub fn fulfill_orders(
+        env: Env,
+        fulfiller: Address,
+        order_ids: Vec<u64>,
+    ) -> Result<Vec<BatchResult>, OrderError> {
+        require_fulfiller(&env, &fulfiller)?;
+        ensure_not_paused(&env, PauseScope::Fulfillment)?;
+        if order_ids.len() > MAX_BATCH_SIZE {
+            return Err(OrderError::BatchTooLarge);
+        }
+
+        let mut results = Vec::new(&env);
+        for order_id in order_ids.iter() {
+            let result = load_order(&env, order_id).and_then(|mut order| {
+                check_deliverable(&env, &order)?;
+                verify_delivery_code(&env, &order, None)?;
+                deliver(&env, &mut order, &fulfiller)
+            });
+            results.push_back(match result {
+                Ok(()) => BatchResult::Ok(order_id),
+                Err(err) => BatchResult::Err(err as u32),
+            });
+        }
+        Ok(results)
+    }
+
+    // Mark order as fulfilled on the strength of a registered attester's signed
+    // statement; a failing signature check aborts the call
+    // Not available for orders created with a delivery code
+    pub fn fulfill_with_attestation(
+        env: Env,
+        order_id: u64,
+        payload: DeliveryAttestation,
+        signature: BytesN<64>,
+    ) -> Result<(), OrderError> {
+        bump_instance(&env);
+        ensure_not_paused(&env, PauseScope::Fulfillment)?;
+
+        let attester_key = OrderKey::Attester(payload.attester.clone());
+        if !env.storage().persistent().has(&attester_key) {
+            return Err(OrderError::Unauthorized);
+        }
+        bump_persistent(&env, &attester_key);
+        if payload.contract != env.current_contract_address()
+            || payload.order_id != order_id
+            || payload.timestamp > env.ledger().timestamp()
+        {
+            return Err(OrderError::InvalidAttestation);
+        }
+        let nonce_key = OrderKey::AttesterNonce(payload.attester.clone(), payload.nonce);
+        if env.storage().persistent().has(&nonce_key) {
+            return Err(OrderError::AttestationReplayed);
+        }
+
+        let mut order = load_order(&env, order_id)?;
+        check_deliverable(&env, &order)?;
+        // An attester doesn't hold the buyer's delivery code, so an order committing
+        // to one can only be fulfilled with the code itself
+        verify_delivery_code(&env, &order, None)?;
+        env.crypto()
+            .ed25519_verify(&payload.attester, &payload.clone().to_xdr(&env), &signature);
+
+        env.storage().persistent().set(&nonce_key, &true);
+        bump_persistent(&env, &nonce_key);
+        order.attester = Some(payload.attester.into());
+        deliver(&env, &mut order, &env.current_contract_address())
+    }
+
+    // Register an ed25519 key whose delivery attestations are trusted (admin only)
+    pub fn add_attester(env: Env, public_key: BytesN<32>) -> Result<(), OrderError> {
+        require_admin(&env);
+        bump_instance(&env);
+
+        let key = OrderKey::Attester(public_key);
+        env.storage().persistent().set(&key, &true);
+        bump_persistent(&env, &key);
+        Ok(())
+    }
+
+    // Stop trusting an attester key (admin only)
+    pub fn remove_attester(env: Env, public_key: BytesN<32>) -> Result<(), OrderError> {
+        require_admin(&env);
+        bump_instance(&env);
+
+        env.storage()
+            .persistent()
+            .remove(&OrderKey::Attester(public_key));
+        Ok(())
+    }
+
+    // Check whether an ed25519 key is a registered attester
+    pub fn is_attester(env: Env, public_key: BytesN<32>) -> bool {
+        bump_instance(&env);
+        env.storage()
+            .persistent()
+            .has(&OrderKey::Attester(public_key))
+    }
+
+    // Record delivery of some units of an order, given as (line index, quantity)
+    // pairs (requires a fulfiller's signature)
+    // The seller is paid the value of those units right away. The order stays
+    // PartiallyFulfilled until every line is complete, at which point it becomes
+    // Delivered and the rest of its escrow waits for completion like any delivered
+    // order. If the order uses a delivery code, every parcel needs it
+    pub fn fulfill_items(
+        env: Env,
+        fulfiller: Address,
+        order_id: u64,
+        items: Vec<(u32, u32)>,
+        delivery_code: Option<Bytes>,
+    ) -> Result<(), OrderError> {
+        require_fulfiller(&env, &fulfiller)?;
+        ensure_not_paused(&env, PauseScope::Fulfillment)?;
+        if items.is_empty() {
+            return Err(OrderError::EmptyOrder);
+        }
+
+        let mut order = load_order(&env, order_id)?;
+        check_deliverable(&env, &order)?;
+        verify_delivery_code(&env, &order, delivery_code)?;
+
+        let mut value: i128 = 0;
+        for (index, quantity) in items.iter() {
+            let line = order.items.get(index).ok_or(OrderError::InvalidLineIndex)?;
+            let done = order.fulfilled.get(index).unwrap();
+            if quantity == 0 || quantity > line.quantity - done {
+                return Err(OrderError::InvalidQuantity);
+            }
+            order.fulfilled.set(index, done + quantity);
+            // Can't overflow, the whole order total was checked at creation
+            value += line.unit_price * quantity as i128;
+        }
+
+        let complete = order
+            .items
+            .iter()
+            .zip(order.fulfilled.iter())
+            .all(|(line, done)| done == line.quantity);
+        if complete {
+            return deliver(&env, &mut order, &fulfiller);
+        }
+
+        order.released += value;
+        if order.status == OrderStatus::PartiallyFulfilled {
+            save_order(&env, &mut order);
+        } else {
+            advance(&env, &mut order, OrderStatus::PartiallyFulfilled)?;
+        }
+        pay_seller(&env, &order, value);
+        publish_order_event(&env, symbol_short!("partial"), &order, &fulfiller);
+        Ok(())
+    }
+
+    // Confirm receipt of a delivered order and release its escrow to the seller
+    // Requires the buyer's signature while the dispute window is open, once it has
+    // passed anyone may complete the order
+    pub fn complete_order(env: Env, order_id: u64) -> Result<(), OrderError> {
+        bump_instance(&env);
+        ensure_not_paused(&env, PauseScope::Fulfillment)?;
+        let config = read_config(&env);
+
+        let mut order = load_order(&env, order_id)?;
+        if order.status != OrderStatus::Delivered {
+            return Err(OrderError::InvalidState);
+        }
+        let actor = if dispute_window_open(&env, &config, &order) {
+            order.buyer.require_auth();
+            order.buyer.clone()
+        } else {
+            env.current_contract_address()
+        };
+
+        advance(&env, &mut order, OrderStatus::Completed)?;
+        pay_seller(&env, &order, escrow_held(&order));
+        publish_order_event(&env, symbol_short!("completed"), &order, &actor);
+        Ok(())
+    }
+
+    // Cancel an order before the seller accepts it and refund the escrow (requires
+    // the buyer's signature)
+    pub fn cancel_order(env: Env, order_id: u64) -> Result<(), OrderError> {
+        bump_instance(&env);
+        ensure_not_paused(&env, PauseScope::Fulfillment)?;
+
+        let mut order = load_order(&env, order_id)?;
+        order.buyer.require_auth();
+        if order.status != OrderStatus::Created {
+            return Err(OrderError::InvalidState);
+        }
+        advance(&env, &mut order, OrderStatus::Cancelled)?;
+        release_escrow(&env, &order, &order.buyer);
+        publish_order_event(&env, symbol_short!("cancelled"), &order, &order.buyer);
+        Ok(())
+    }
+
+    // Decline an order before it ships and refund the escrow to the buyer (requires
+    // the seller's signature)
+    pub fn reject_order(env: Env, order_id: u64, reason_code: u32) -> Result<(), OrderError> {
+        bump_instance(&env);
+        ensure_not_paused(&env, PauseScope::Fulfillment)?;
+
+        let mut order = load_order(&env, order_id)?;
+        order.seller.require_auth();
+        advance(&env, &mut order, OrderStatus::Cancelled)?;
+        release_escrow(&env, &order, &order.buyer);
+        update_reputation(&env, &order.seller, |record| record.cancelled += 1);
+
+        env.events().publish(
+            (
+                symbol_short!("order"),
+                symbol_short!("rejected"),
+                order_id,
+                order.buyer.clone(),
+            ),
+            RejectionEvent {
+                version: EVENT_VERSION,
+                seller: order.seller.clone(),
+                reason_code,
+                timestamp: env.ledger().timestamp(),
+            },
+        );
+        Ok(())
+    }
+
+    // Dispute a delivered order within the dispute window, freezing its escrow until
+    // an arbiter rules on it (requires the buyer's signature)
+    pub fn open_dispute(
+        env: Env,
+        order_id: u64,
+        reason_hash: BytesN<32>,
+    ) -> Result<(), OrderError> {
+        bump_instance(&env);
+        ensure_not_paused(&env, PauseScope::Fulfillment)?;
+        let config = read_config(&env);
+
+        let mut order = load_order(&env, order_id)?;
+        order.buyer.require_auth();
+        if order.status != OrderStatus::Delivered {
+            return Err(OrderError::InvalidState);
+        }
+        if !dispute_window_open(&env, &config, &order) {
+            return Err(OrderError::DisputeWindowClosed);
+        }
+
+        advance(&env, &mut order, OrderStatus::Disputed)?;
+        let dispute = Dispute {
+            reason_hash,
+            opened_at: env.ledger().timestamp(),
+            ruling: Ruling::Pending,
+            arbiter: None,
+            resolved_at: None,
+        };
+        save_dispute(&env, order_id, &dispute);
+        update_reputation(&env, &order.seller, |record| record.disputed += 1);
+        publish_order_event(&env, symbol_short!("disputed"), &order, &order.buyer);
+        Ok(())
+    }
+
+    // Rule on a disputed order and pay out its escrow accordingly (requires an
+    // arbiter's signature). The order ends Completed when the seller gets the whole
+    // escrow and Refunded otherwise
+    pub fn resolve_dispute(
+        env: Env,
+        arbiter: Address,
+        order_id: u64,
+        ruling: Ruling,
+    ) -> Result<(), OrderError> {
+        arbiter.require_auth();
+        bump_instance(&env);
+        if !has_role(&env, &OrderKey::Arbiter(arbiter.clone())) {
+            return Err(OrderError::Unauthorized);
+        }
+        ensure_not_paused(&env, PauseScope::Fulfillment)?;
+
+        let mut order = load_order(&env, order_id)?;
+        if order.status != OrderStatus::Disputed {
+            return Err(OrderError::InvalidState);
+        }
+        let held = escrow_held(&order);
+        let seller_amount = match ruling {
+            Ruling::Pending => return Err(OrderError::InvalidRuling),
+            Ruling::ReleaseToSeller => held,
+            Ruling::RefundBuyer => 0,
+            Ruling::Split(bps) if bps > MAX_BPS => return Err(OrderError::InvalidRuling),
+            Ruling::Split(bps) => bps_share(held, bps),
+        };
+        let buyer_amount = held - seller_amount;
+
+        let next = if buyer_amount == 0 {
+            OrderStatus::Completed
+        } else {
+            OrderStatus::Refunded
+        };
+        advance(&env, &mut order, next)?;
+        split_escrow(&env, &order, seller_amount);
+        if buyer_amount > 0 {
+            update_reputation(&env, &order.seller, |record| record.disputes_lost += 1);
+        }
+
+        let mut dispute = load_dispute(&env, order_id)?;
+        dispute.ruling = ruling;
+        dispute.arbiter = Some(arbiter.clone());
+        dispute.resolved_at = Some(env.ledger().timestamp());
+        save_dispute(&env, order_id, &dispute);
+
+        env.events().publish(
+            (
+                symbol_short!("order"),
+                symbol_short!("resolved"),
+                order_id,
+                order.buyer.clone(),
+            ),
+            DisputeEvent {
+                version: EVENT_VERSION,
+                arbiter,
+                ruling,
+                seller_amount,
+                buyer_amount,
+                timestamp: env.ledger().timestamp(),
+            },
+        );
+        Ok(())
+    }
+
+    // View the dispute opened over an order
+    pub fn get_dispute(env: Env, order_id: u64) -> Result<Dispute, OrderError> {
+        bump_instance(&env);
+        load_dispute(&env, order_id)
+    }
+
+    // Append a logistics checkpoint to an order's shipment timeline (requires the
+    // signature of a fulfiller or courier). Settled orders can't be tracked anymore
+    pub fn add_checkpoint(
+        env: Env,
+        actor: Address,
+        order_id: u64,
+        status_code: u32,
+        location_hash: BytesN<32>,
+    ) -> Result<(), OrderError> {
+        actor.require_auth();
+        bump_instance(&env);
+        if !has_role(&env, &OrderKey::Fulfiller(actor.clone()))
+            && !has_role(&env, &OrderKey::Courier(actor.clone()))
+        {
+            return Err(OrderError::Unauthorized);
+        }
+        ensure_not_paused(&env, PauseScope::Fulfillment)?;
+
+        let order = load_order(&env, order_id)?;
+        if matches!(
+            order.status,
+            OrderStatus::Completed | OrderStatus::Cancelled | OrderStatus::Refunded
+        ) {
+            return Err(OrderError::InvalidState);
+        }
+        let mut timeline = read_timeline(&env, order_id);
+        if timeline.len() >= MAX_TIMELINE_LEN {
+            return Err(OrderError::TimelineFull);
+        }
+        timeline.push_back(Checkpoint {
+            status_code,
+            timestamp: env.ledger().timestamp(),
+            actor: actor.clone(),
+            location_hash,
+        });
+
+        let key = OrderKey::Timeline(order_id);
+        env.storage().persistent().set(&key, &timeline);
+        bump_persistent(&env, &key);
+        publish_order_event(&env, symbol_short!("tracking"), &order, &actor);
+        Ok(())
+    }
+
+    // View an order's shipment timeline, oldest checkpoint first, starting at
+    // position `cursor` (0 when None)
+    // Returns at most `limit` checkpoints and the cursor of the next page, or None
+    // when there are no more checkpoints
+    pub fn get_timeline(
+        env: Env,
+        order_id: u64,
+        cursor: Option<u32>,
+        limit: u32,
+    ) -> Result<(Vec<Checkpoint>, Option<u32>), OrderError> {
+        bump_instance(&env);
+        if limit == 0 {
+            return Err(OrderError::InvalidLimit);
+        }
+        load_order(&env, order_id)?;
+
+        let timeline = read_timeline(&env, order_id);
+        let count = timeline.len();
+        let start = cursor.unwrap_or(0).min(count);
+        let end = start.saturating_add(limit).min(count);
+
+        let next = if end < count { Some(end) } else { None };
+        Ok((timeline.slice(start..end), next))
+    }
+
+    // Rate a fulfilled order, once (requires the buyer's signature)
+    pub fn rate_order(
+        env: Env,
+        order_id: u64,
+        rating: u32,
+        content_hash: BytesN<32>,
+    ) -> Result<(), OrderError> {
+        bump_instance(&env);
+        ensure_not_paused(&env, PauseScope::Fulfillment)?;
+
+        let order = load_order(&env, order_id)?;
+        order.buyer.require_auth();
+        if !matches!(
+            order.status,
+            OrderStatus::Delivered | OrderStatus::Completed
+        ) {
+            return Err(OrderError::InvalidState);
+        }
+        if !(MIN_RATING..=MAX_RATING).contains(&rating) {
+            return Err(OrderError::InvalidRating);
+        }
+        let review_key = OrderKey::Review(order_id);
+        if env.storage().persistent().has(&review_key) {
+            return Err(OrderError::AlreadyRated);
+        }
+
+        let review = Review {
+            rating,
+            content_hash,
             timestamp: env.ledger().timestamp(),
         };
+        env.storage().persistent().set(&review_key, &review);
+        bump_persistent(&env, &review_key);
+
+        let mut aggregate = read_seller_rating(&env, &order.seller);
+        aggregate.sum += rating as u64;
+        aggregate.count += 1;
+        let rating_key = OrderKey::SellerRating(order.seller.clone());
+        env.storage().persistent().set(&rating_key, &aggregate);
+        bump_persistent(&env, &rating_key);
+
+        publish_order_event(&env, symbol_short!("rated"), &order, &order.buyer);
+        Ok(())
+    }
+
+    // View the buyer's review of an order
+    pub fn get_review(env: Env, order_id: u64) -> Result<Review, OrderError> {
+        bump_instance(&env);
+        let key = OrderKey::Review(order_id);
+        let review: Review = env
+            .storage()
+            .persistent()
+            .get(&key)
+            .ok_or(OrderError::NotFound)?;
+        bump_persistent(&env, &key);
+        Ok(review)
+    }
 
-        env.storage().instance().set(&OrderKey::Order(count), &new_order);
-        env.storage().instance().set(&OrderKey::OrderCount, &count);
+    // View the ratings a seller received so far (zero when not rated yet)
+    pub fn get_seller_rating(env: Env, seller: Address) -> SellerRating {
+        bump_instance(&env);
+        read_seller_rating(&env, &seller)
+    }
 
-        count
+    // View a seller's fulfillment track record (all zero for a seller without
+    // history), meant to be queried by other contracts routing orders
+    pub fn reputation(env: Env, seller: Address) -> SellerReputation {
+        bump_instance(&env);
+        read_reputation(&env, &seller)
     }
 
-    // Mark order as fulfilled
-    pub fn fulfill_order(env: Env, order_id: u64) {
-        let mut order: Order = env.storage().instance().get(&OrderKey::Order(order_id)).expect("Order not found");
+    // Reclaim the escrow of an order that was not delivered before its deadline
+    // (requires the buyer's signature)
+    pub fn claim_refund(env: Env, order_id: u64) -> Result<(), OrderError> {
+        bump_instance(&env);
+        ensure_not_paused(&env, PauseScope::Fulfillment)?;
 
-        if order.is_fulfilled {
-            panic!("Order already fulfilled");
+        let mut order = load_order(&env, order_id)?;
+        order.buyer.require_auth();
+        if !matches!(
+            order.status,
+            OrderStatus::Created
+                | OrderStatus::Accepted
+                | OrderStatus::Shipped
+                | OrderStatus::PartiallyFulfilled
+        ) {
+            return Err(OrderError::InvalidState);
+        }
+        if !deadline_passed(&env, &order) {
+            return Err(OrderError::DeadlineNotReached);
         }
 
-        order.is_fulfilled = true;
-        env.storage().instance().set(&OrderKey::Order(order_id), &order);
+        advance(&env, &mut order, OrderStatus::Refunded)?;
+        release_escrow(&env, &order, &order.buyer);
+        update_reputation(&env, &order.seller, |record| record.cancelled += 1);
+        publish_order_event(&env, symbol_short!("refunded"), &order, &order.buyer);
+        Ok(())
     }
 
     // View an order
-    pub fn get_order(env: Env, order_id: u64) -> Order {
-        env.storage().instance().get(&OrderKey::Order(order_id)).expect("Order not found")
+    pub fn get_order(env: Env, order_id: u64) -> Result<Order, OrderError> {
+        bump_instance(&env);
+        load_order(&env, order_id)
+    }
+
+    // List a buyer's orders, oldest first, starting at position `cursor` (0 when None)
+    // Returns at most `limit` orders (capped at MAX_PAGE_SIZE) and the cursor of the
+    // next page, or None when there are no more orders
+    pub fn list_orders_by_buyer(
+        env: Env,
+        buyer: Address,
+        cursor: Option<u32>,
+        limit: u32,
+    ) -> Result<(Vec<Order>, Option<u32>), OrderError> {
+        bump_instance(&env);
+        if limit == 0 {
+            return Err(OrderError::InvalidLimit);
+        }
+
+        let count_key = OrderKey::BuyerOrderCount(buyer.clone());
+        let count: u32 = env.storage().persistent().get(&count_key).unwrap_or(0);
+        if count > 0 {
+            bump_persistent(&env, &count_key);
+        }
+        let start = cursor.unwrap_or(0).min(count);
+        let end = start.saturating_add(limit.min(MAX_PAGE_SIZE)).min(count);
+
+        let mut orders = Vec::new(&env);
+        for position in start..end {
+            let slot_key = OrderKey::BuyerOrder(buyer.clone(), position);
+            let order_id: u64 = env.storage().persistent().get(&slot_key).unwrap();
+            bump_persistent(&env, &slot_key);
+            orders.push_back(load_order(&env, order_id)?);
+        }
+
+        let next = if end < count { Some(end) } else { None };
+        Ok((orders, next))
+    }
+
+    // List a seller's orders in `status`, in the order they entered it, starting at
+    // order id `cursor` (the head of the queue when None)
+    // Returns at most `limit` orders (capped at MAX_PAGE_SIZE) and the cursor of the
+    // next page, or None when there are no more orders. A cursor whose order has since
+    // changed status is rejected with InvalidCursor
+    pub fn list_orders_by_seller(
+        env: Env,
+        seller: Address,
+        status: OrderStatus,
+        cursor: Option<u64>,
+        limit: u32,
+    ) -> Result<(Vec<Order>, Option<u64>), OrderError> {
+        bump_instance(&env);
+        if limit == 0 {
+            return Err(OrderError::InvalidLimit);
+        }
+
+        let mut next = match cursor {
+            Some(order_id) => {
+                let order = load_order(&env, order_id)?;
+                if order.seller != seller || order.status != status {
+                    return Err(OrderError::InvalidCursor);
+                }
+                Some(order_id)
+            }
+            None => read_queue_ends(&env, &seller, status).map(|ends| ends.head),
+        };
+
+        let mut orders = Vec::new(&env);
+        while let Some(order_id) = next {
+            if orders.len() == limit.min(MAX_PAGE_SIZE) {
+                break;
+            }
+            orders.push_back(load_order(&env, order_id)?);
+            next = read_queue_node(&env, order_id).next;
+        }
+        Ok((orders, next))
+    }
+
+    // Oldest order of a seller still waiting in the Created status, if any
+    pub fn next_pending_order(env: Env, seller: Address) -> Result<Option<Order>, OrderError> {
+        bump_instance(&env);
+        match read_queue_ends(&env, &seller, OrderStatus::Created) {
+            Some(ends) => Ok(Some(load_order(&env, ends.head)?)),
+            None => Ok(None),
+        }
+    }
+
+    // Rewrite the orders with ids `from` to `to` (inclusive) still stored in an older
+    // schema version in the latest one, returning how many were upgraded (admin only)
+    // Covers at most MAX_PAGE_SIZE ids per call, unknown ids are skipped
+    pub fn migrate_range(env: Env, from: u64, to: u64) -> Result<u32, OrderError> {
+        require_admin(&env);
+        bump_instance(&env);
+        if to < from || to - from >= MAX_PAGE_SIZE as u64 {
+            return Err(OrderError::InvalidLimit);
+        }
+
+        let mut migrated = 0;
+        for order_id in from..=to {
+            if let Some((order, true)) = read_order(&env, order_id) {
+                write_order(&env, &order);
+                migrated += 1;
+            }
+        }
+        Ok(migrated)
+    }
+
+    // Extend an order's TTL to the network maximum so it is never archived (admin only)
+    pub fn extend_order_ttl(env: Env, order_id: u64) -> Result<(), OrderError> {
+        require_admin(&env);
+        bump_instance(&env);
+
+        let (order, _) = read_order(&env, order_id).ok_or(OrderError::NotFound)?;
+        let max_ttl = env.storage().max_ttl();
+        let storage = env.storage().persistent();
+        // Along with every entry kept for the order, so that none is archived first
+        storage.extend_ttl(&OrderKey::Order(order_id), max_ttl, max_ttl);
+        storage.extend_ttl(&OrderKey::QueueNode(order_id), max_ttl, max_ttl);
+        if let Some(slot) = order.buyer_slot {
+            storage.extend_ttl(&OrderKey::BuyerOrder(order.buyer, slot), max_ttl, max_ttl);
+        }
+        for key in [
+            OrderKey::Dispute(order_id),
+            OrderKey::Timeline(order_id),
+            OrderKey::Review(order_id),
+        ] {
+            if storage.has(&key) {
+                storage.extend_ttl(&key, max_ttl, max_ttl);
+            }
+        }
+        Ok(())
+    }
+}
+
+// Validate and store a new order, pulling its total into escrow
+fn create(
+    env: &Env,
+    buyer: Address,
+    seller: Address,
+    items: Vec<LineItem>,
+    token: Address,
+    delivery_hash: Option<BytesN<32>>,
+    deadline: Option<u64>,
+) -> Result<u64, OrderError> {
+    ensure_not_paused(env, PauseScope::Creation)?;
+    let config = read_config(env);
+    let amount = order_total(&config, &items)?;
+    let mut fulfilled = Vec::new(env);
+    for _ in 0..items.len() {
+        fulfilled.push_back(0u32);
+    }
+    let timestamp = env.ledger().timestamp();
+    let deadline = match deadline {
+        Some(deadline) if deadline <= timestamp => return Err(OrderError::InvalidDeadline),
+        Some(deadline) => deadline,
+        None => timestamp.saturating_add(config.fulfillment_deadline),
+    };
+    match read_seller(env, &seller) {
+        None => return Err(OrderError::SellerNotFound),
+        Some(profile) if profile.status != SellerStatus::Approved => {
+            return Err(OrderError::SellerNotApproved)
+        }
+        Some(_) => {}
+    }
+    // Checked up front so that a short balance fails as a contract error, which a
+    // batch can report, rather than trapping in the token transfer
+    let token_client = token::Client::new(env, &token);
+    if token_client.balance(&buyer) < amount {
+        return Err(OrderError::InsufficientFunds);
+    }
+
+    let mut count: u64 = env
+        .storage()
+        .instance()
+        .get(&OrderKey::OrderCount)
+        .unwrap_or(0);
+    count += 1;
+    let buyer_slot = index_buyer_order(env, &buyer, count);
+
+    let mut new_order = Order {
+        order_id: count,
+        buyer,
+        seller,
+        items,
+        token,
+        amount,
+        fulfilled,
+        released: 0,
+        delivery_hash: delivery_hash.map(Bytes::from),
+        attester: None,
+        status: OrderStatus::Created,
+        status_times: Map::from_array(env, [(OrderStatus::Created, timestamp)]),
+        timestamp,
+        deadline,
+        updated_at: timestamp,
+        fee_bps: config.fee_bps,
+        paused_offset: paused_time(env),
+        buyer_slot: Some(buyer_slot),
+    };
+
+    token_client.transfer(&new_order.buyer, &env.current_contract_address(), &amount);
+
+    save_order(env, &mut new_order);
+    env.storage().instance().set(&OrderKey::OrderCount, &count);
+    enqueue(env, &new_order.seller, OrderStatus::Created, count);
+    publish_order_event(env, symbol_short!("created"), &new_order, &new_order.buyer);
+
+    Ok(count)
+}
+
+// Append an order to its buyer's index, returning its position there
+fn index_buyer_order(env: &Env, buyer: &Address, order_id: u64) -> u32 {
+    let count_key = OrderKey::BuyerOrderCount(buyer.clone());
+    let position: u32 = env.storage().persistent().get(&count_key).unwrap_or(0);
+
+    let slot_key = OrderKey::BuyerOrder(buyer.clone(), position);
+    env.storage().persistent().set(&slot_key, &order_id);
+    env.storage().persistent().set(&count_key, &(position + 1));
+    for key in [slot_key, count_key] {
+        bump_persistent(env, &key);
+    }
+    position
+}
+
+// Check line items against the config limits and sum them up
+fn order_total(config: &Config, items: &Vec<LineItem>) -> Result<i128, OrderError> {
+    if items.is_empty() {
+        return Err(OrderError::EmptyOrder);
+    }
+    if items.len() > config.max_items {
+        return Err(OrderError::TooManyItems);
+    }
+
+    let mut total: i128 = 0;
+    for item in items.iter() {
+        if item.sku.len() > config.max_product_len {
+            return Err(OrderError::ProductTooLong);
+        }
+        if item.quantity == 0 || item.quantity > config.max_quantity {
+            return Err(OrderError::InvalidQuantity);
+        }
+        if item.unit_price <= 0 {
+            return Err(OrderError::InvalidAmount);
+        }
+        let line_total = item
+            .unit_price
+            .checked_mul(item.quantity as i128)
+            .ok_or(OrderError::Overflow)?;
+        total = total.checked_add(line_total).ok_or(OrderError::Overflow)?;
     }
+    Ok(total)
 }
+
+fn require_admin(env: &Env) -> Address {
+    let admin: Address = env.storage().instance().get(&OrderKey::Admin).unwrap();
+    admin.require_auth();
+    admin
+}
+
+fn require_fulfiller(env: &Env, fulfiller: &Address) -> Result<(), OrderError> {
+    fulfiller.require_auth();
+    bump_instance(env);
+
+    if !has_role(env, &OrderKey::Fulfiller(fulfiller.clone())) {
+        return Err(OrderError::Unauthorized);
+    }
+    Ok(())
+}
+
+fn validate_config(config: &Config) -> Result<(), OrderError> {
+    if config.fee_bps > MAX_FEE_BPS {
+        return Err(OrderError::InvalidFee);
+    }
+    Ok(())
+}
+
+// Read the config, rewriting it in the latest schema version if it was stored in
+// an older one
+// A legacy paused flag paused the whole order flow; it becomes the pause state
+fn read_config(env: &Env) -> Config {
+    let raw: Val = env.storage().instance().get(&OrderKey::Config).unwrap();
+    if let Ok(StoredConfig::V2(config)) = StoredConfig::try_from_val(env, &raw) {
+        return config;
+    }
+    // Written before the config was versioned, as a bare V1 struct
+    let legacy = ConfigV1::try_from_val(env, &raw).unwrap();
+    if legacy.paused {
+        env.storage().instance().set(
+            &OrderKey::Paused,
+            &PauseState {
+                creation: true,
+                fulfillment: true,
+            },
+        );
+        // When the legacy pause began isn't known, it counts from the upgrade on
+        env.storage().instance().set(
+            &OrderKey::PauseClock,
+            &PauseClock {
+                total: 0,
+                since: env.ledger().timestamp(),
+            },
+        );
+    }
+    let config = legacy.upgrade();
+    write_config(env, &config);
+    config
+}
+
+// Write the config in the latest schema version
+fn write_config(env: &Env, config: &Config) {
+    env.storage()
+        .instance()
+        .set(&OrderKey::Config, &StoredConfig::V2(config.clone()));
+}
+
+fn read_pause_state(env: &Env) -> PauseState {
+    // Migrates a legacy config first, so that its paused flag is not lost
+    read_config(env);
+    env.storage()
+        .instance()
+        .get(&OrderKey::Paused)
+        .unwrap_or(PauseState {
+            creation: false,
+            fulfillment: false,
+        })
+}
+
+fn read_pause_clock(env: &Env) -> PauseClock {
+    env.storage()
+        .instance()
+        .get(&OrderKey::PauseClock)
+        .unwrap_or(PauseClock { total: 0, since: 0 })
+}
+
+// Seconds the fulfillment scope has spent paused so far, including a pause still
+// in progress
+fn paused_time(env: &Env) -> u64 {
+    let state = read_pause_state(env);
+    let clock = read_pause_clock(env);
+    if state.fulfillment {
+        clock.total + (env.ledger().timestamp() - clock.since)
+    } else {
+        clock.total
+    }
+}
+
+// How far pauses since the order's last status change push its timers back
+fn pause_extension(env: &Env, order: &Order) -> u64 {
+    paused_time(env) - order.paused_offset
+}
+
+// Whether the order's fulfillment deadline has passed
+fn deadline_passed(env: &Env, order: &Order) -> bool {
+    env.ledger().timestamp() > order.deadline.saturating_add(pause_extension(env, order))
+}
+
+// Fail with Paused if `scope` is paused
+fn ensure_not_paused(env: &Env, scope: PauseScope) -> Result<(), OrderError> {
+    let state = read_pause_state(env);
+    if (scope.covers_creation() && state.creation)
+        || (scope.covers_fulfillment() && state.fulfillment)
+    {
+        return Err(OrderError::Paused);
+    }
+    Ok(())
+}
+
+// Set the pause flags covered by `scope`, publishing ("paused"|"unpaused", caller)
+fn set_paused(env: &Env, caller: &Address, scope: PauseScope, paused: bool) {
+    let mut state = read_pause_state(env);
+    if scope.covers_creation() {
+        state.creation = paused;
+    }
+    if scope.covers_fulfillment() && state.fulfillment != paused {
+        let mut clock = read_pause_clock(env);
+        let now = env.ledger().timestamp();
+        if paused {
+            clock.since = now;
+        } else {
+            clock.total += now - clock.since;
+        }
+        env.storage().instance().set(&OrderKey::PauseClock, &clock);
+        state.fulfillment = paused;
+    }
+    env.storage().instance().set(&OrderKey::Paused, &state);
+
+    let action = if paused {
+        symbol_short!("paused")
+    } else {
+        symbol_short!("unpaused")
+    };
+    env.events().publish((action, caller.clone()), scope);
+}
+
+// Whether a role entry (fulfiller, arbiter) exists, keeping it alive if so
+fn has_role(env: &Env, key: &OrderKey) -> bool {
+    let granted = env.storage().persistent().has(key);
+    if granted {
+        bump_persistent(env, key);
+    }
+    granted
+}
+
+fn read_seller(env: &Env, seller: &Address) -> Option<SellerProfile> {
+    let key = OrderKey::Seller(seller.clone());
+    let profile: Option<SellerProfile> = env.storage().persistent().get(&key);
+    if profile.is_some() {
+        bump_persistent(env, &key);
+    }
+    profile
+}
+
+fn write_seller(env: &Env, seller: &Address, profile: &SellerProfile) {
+    let key = OrderKey::Seller(seller.clone());
+    env.storage().persistent().set(&key, profile);
+    bump_persistent(env, &key);
+}
+
+fn set_seller_status(env: &Env, seller: &Address, status: SellerStatus) -> Result<(), OrderError> {
+    require_admin(env);
+    bump_instance(env);
+
+    let mut profile = read_seller(env, seller).ok_or(OrderError::SellerNotFound)?;
+    profile.status = status;
+    write_seller(env, seller, &profile);
+    Ok(())
+}
+
+fn bump_persistent(env: &Env, key: &OrderKey) {
+    env.storage()
+        .persistent()
+        .extend_ttl(key, ORDER_LIFETIME_THRESHOLD, ORDER_BUMP_AMOUNT);
+}
+
+fn bump_instance(env: &Env) {
+    env.storage()
+        .instance()
+        .extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+}
+
+// Read an order entry in whichever schema version it was stored, converted to the
+// latest one, along with whether it was stored in an older version
+fn read_order(env: &Env, order_id: u64) -> Option<(Order, bool)> {
+    let raw: Val = env.storage().persistent().get(&OrderKey::Order(order_id))?;
+    Some(match StoredOrder::try_from_val(env, &raw) {
+        Ok(StoredOrder::V2(order)) => (order, false),
+        // Written before orders were versioned, as a bare V1 struct
+        Err(_) => (OrderV1::try_from_val(env, &raw).unwrap().upgrade(), true),
+    })
+}
+
+// Read an order and keep its entry and queue node alive, rewriting it in the
+// latest schema version if it was stored in an older one
+fn load_order(env: &Env, order_id: u64) -> Result<Order, OrderError> {
+    let (order, outdated) = read_order(env, order_id).ok_or(OrderError::NotFound)?;
+    if outdated {
+        write_order(env, &order);
+    } else {
+        bump_persistent(env, &OrderKey::Order(order_id));
+    }
+    bump_persistent(env, &OrderKey::QueueNode(order_id));
+    Ok(order)
+}
+
+// Record a change to an order and persist it
+fn save_order(env: &Env, order: &mut Order) {
+    order.updated_at = env.ledger().timestamp();
+    write_order(env, order);
+}
+
+// Write an order in the latest schema version and keep its entry alive
+fn write_order(env: &Env, order: &Order) {
+    let key = OrderKey::Order(order.order_id);
+    env.storage()
+        .persistent()
+        .set(&key, &StoredOrder::V2(order.clone()));
+    bump_persistent(env, &key);
+}
+
+// Move an order to `next`, recording when it happened, and persist it
+fn advance(env: &Env, order: &mut Order, next: OrderStatus) -> Result<(), OrderError> {
+    if !order.status.can_transition_to(next) {
+        return Err(OrderError::InvalidState);
+    }
+    dequeue(env, &order.seller, order.status, order.order_id);
+    enqueue(env, &order.seller, next, order.order_id);
+    // Settle the pauses so far into the deadline, later ones count from this change
+    let paused = paused_time(env);
+    order.deadline = order.deadline.saturating_add(paused - order.paused_offset);
+    order.paused_offset = paused;
+    order.status = next;
+    order.status_times.set(next, env.ledger().timestamp());
+    save_order(env, order);
+    Ok(())
+}
+
+fn read_queue_ends(env: &Env, seller: &Address, status: OrderStatus) -> Option<QueueEnds> {
+    let key = OrderKey::SellerQueue(seller.clone(), status);
+    let ends: Option<QueueEnds> = env.storage().persistent().get(&key);
+    if ends.is_some() {
+        bump_persistent(env, &key);
+    }
+    ends
+}
+
+fn read_queue_node(env: &Env, order_id: u64) -> QueueNode {
+    let key = OrderKey::QueueNode(order_id);
+    let node: QueueNode = env.storage().persistent().get(&key).unwrap();
+    bump_persistent(env, &key);
+    node
+}
+
+fn write_queue_node(env: &Env, order_id: u64, node: &QueueNode) {
+    let key = OrderKey::QueueNode(order_id);
+    env.storage().persistent().set(&key, node);
+    bump_persistent(env, &key);
+}
+
+// Append an order to the tail of the (seller, status) queue
+fn enqueue(env: &Env, seller: &Address, status: OrderStatus, order_id: u64) {
+    let key = OrderKey::SellerQueue(seller.clone(), status);
+    let ends = match read_queue_ends(env, seller, status) {
+        Some(ends) => {
+            let mut tail = read_queue_node(env, ends.tail);
+            tail.next = Some(order_id);
+            write_queue_node(env, ends.tail, &tail);
+            write_queue_node(
+                env,
+                order_id,
+                &QueueNode {
+                    prev: Some(ends.tail),
+                    next: None,
+                },
+            );
+            QueueEnds {
+                head: ends.head,
+                tail: order_id,
+            }
+        }
+        None => {
+            write_queue_node(
+                env,
+                order_id,
+                &QueueNode {
+                    prev: None,
+                    next: None,
+                },
+            );
+            QueueEnds {
+                head: order_id,
+                tail: order_id,
+            }
+        }
+    };
+    env.storage().persistent().set(&key, &ends);
+    bump_persistent(env, &key);
+}
+
+// Unlink an order from the (seller, status) queue it currently sits in
+fn dequeue(env: &Env, seller: &Address, status: OrderStatus, order_id: u64) {
+    let key = OrderKey::SellerQueue(seller.clone(), status);
+    let mut ends = read_queue_ends(env, seller, status).unwrap();
+    let node = read_queue_node(env, order_id);
+
+    match node.prev {
+        Some(prev_id) => {
+            let mut prev = read_queue_node(env, prev_id);
+            prev.next = node.next;
+            write_queue_node(env, prev_id, &prev);
+        }
+        None => {
+            if let Some(next_id) = node.next {
+                ends.head = next_id;
+            }
+        }
+    }
+    match node.next {
+        Some(next_id) => {
+            let mut next = read_queue_node(env, next_id);
+            next.prev = node.prev;
+            write_queue_node(env, next_id, &next);
+        }
+        None => {
+            if let Some(prev_id) = node.prev {
+                ends.tail = prev_id;
+            }
+        }
+    }
+
+    if node.prev.is_none() && node.next.is_none() {
+        env.storage().persistent().remove(&key);
+    } else if node.prev.is_none() || node.next.is_none() {
+        env.storage().persistent().set(&key, &ends);
+    }
+}
+
+// Whether an order can still be marked delivered
+fn check_deliverable(env: &Env, order: &Order) -> Result<(), OrderError> {
+    if matches!(
+        order.status,
+        OrderStatus::Delivered | OrderStatus::Completed
+    ) {
+        return Err(OrderError::AlreadyFulfilled);
+    }
+    if order.status == OrderStatus::Cancelled {
+        return Err(OrderError::InvalidState);
+    }
+    if deadline_passed(env, order) {
+        return Err(OrderError::DeadlineExpired);
+    }
+    Ok(())
+}
+
+// Mark an order delivered, with every unit of it fulfilled. Its remaining escrow
+// stays locked until it is completed or a dispute over it is resolved
+fn deliver(env: &Env, order: &mut Order, actor: &Address) -> Result<(), OrderError> {
+    let mut fulfilled = Vec::new(env);
+    for line in order.items.iter() {
+        fulfilled.push_back(line.quantity);
+    }
+    order.fulfilled = fulfilled;
+    advance(env, order, OrderStatus::Delivered)?;
+    let fulfill_time = env.ledger().timestamp().saturating_sub(order.timestamp);
+    update_reputation(env, &order.seller, |record| {
+        record.fulfilled += 1;
+        record.total_fulfill_time = record.total_fulfill_time.saturating_add(fulfill_time);
+        record.avg_fulfill_time = record.total_fulfill_time / record.fulfilled as u64;
+    });
+    publish_order_event(env, symbol_short!("fulfilled"), order, actor);
+    Ok(())
+}
+
+// Check a delivery code against the order's commitment, if it has one
+fn verify_delivery_code(env: &Env, order: &Order, code: Option<Bytes>) -> Result<(), OrderError> {
+    let Some(expected) = &order.delivery_hash else {
+        return Ok(());
+    };
+    let code = code.ok_or(OrderError::InvalidDeliveryCode)?;
+    if Bytes::from(env.crypto().sha256(&code).to_bytes()) != *expected {
+        return Err(OrderError::InvalidDeliveryCode);
+    }
+    Ok(())
+}
+
+// Part of an order's amount still held in escrow
+fn escrow_held(order: &Order) -> i128 {
+    order.amount - order.released
+}
+
+// Return what is left of an order's escrow to `to`
+fn release_escrow(env: &Env, order: &Order, to: &Address) {
+    token::Client::new(env, &order.token).transfer(
+        &env.current_contract_address(),
+        to,
+        &escrow_held(order),
+    );
+}
+
+// Pay `seller_amount` of an order's remaining escrow to the seller and the rest back
+// to the buyer
+fn split_escrow(env: &Env, order: &Order, seller_amount: i128) {
+    let client = token::Client::new(env, &order.token);
+    let contract = env.current_contract_address();
+    let buyer_amount = escrow_held(order) - seller_amount;
+    pay_seller(env, order, seller_amount);
+    if buyer_amount > 0 {
+        client.transfer(&contract, &order.buyer, &buyer_amount);
+    }
+}
+
+// Pay `amount` of an order's escrow to the seller, keeping the order's platform fee
+fn pay_seller(env: &Env, order: &Order, amount: i128) {
+    let fee = platform_fee(amount, order.fee_bps);
+    if fee > 0 {
+        let balance = read_fee_balance(env, &order.token);
+        write_fee_balance(env, &order.token, balance + fee);
+    }
+    if amount > fee {
+        token::Client::new(env, &order.token).transfer(
+            &env.current_contract_address(),
+            &order.seller,
+            &(amount - fee),
+        );
+    }
+}
+
+// Platform fee on a payment of `amount`, rounded down so sellers never pay more
+// than `fee_bps` of it
+fn platform_fee(amount: i128, fee_bps: u32) -> i128 {
+    bps_share(amount, fee_bps)
+}
+
+fn read_fee_balance(env: &Env, token: &Address) -> i128 {
+    let key = OrderKey::FeeBalance(token.clone());
+    let balance: Option<i128> = env.storage().persistent().get(&key);
+    if balance.is_some() {
+        bump_persistent(env, &key);
+    }
+    balance.unwrap_or(0)
+}
+
+fn write_fee_balance(env: &Env, token: &Address, balance: i128) {
+    let key = OrderKey::FeeBalance(token.clone());
+    env.storage().persistent().set(&key, &balance);
+    bump_persistent(env, &key);
+}
+
+// `bps` basis points of `amount`, rounded down, without overflowing for any amount
+fn bps_share(amount: i128, bps: u32) -> i128 {
+    let (whole, bps) = (MAX_BPS as i128, bps as i128);
+    amount / whole * bps + amount % whole * bps / whole
+}
+
+// Whether the buyer may still dispute a delivered order, the window being extended
+// by fulfillment pauses since delivery
+fn dispute_window_open(env: &Env, config: &Config, order: &Order) -> bool {
+    let delivered_at = order.status_times.get(OrderStatus::Delivered).unwrap();
+    let closes_at = delivered_at
+        .saturating_add(config.dispute_window)
+        .saturating_add(pause_extension(env, order));
+    env.ledger().timestamp() <= closes_at
+}
+
+// Read an order's shipment timeline (empty when nothing was recorded yet)
+fn read_timeline(env: &Env, order_id: u64) -> Vec<Checkpoint> {
+    let key = OrderKey::Timeline(order_id);
+    match env.storage().persistent().get(&key) {
+        Some(timeline) => {
+            bump_persistent(env, &key);
+            timeline
+        }
+        None => Vec::new(env),
+    }
+}
+
+fn read_seller_rating(env: &Env, seller: &Address) -> SellerRating {
+    let key = OrderKey::SellerRating(seller.clone());
+    match env.storage().persistent().get(&key) {
+        Some(aggregate) => {
+            bump_persistent(env, &key);
+            aggregate
+        }
+        None => SellerRating { sum: 0, count: 0 },
+    }
+}
+
+fn read_reputation(env: &Env, seller: &Address) -> SellerReputation {
+    let key = OrderKey::Reputation(seller.clone());
+    match env.storage().persistent().get(&key) {
+        Some(record) => {
+            bump_persistent(env, &key);
+            record
+        }
+        None => SellerReputation {
+            fulfilled: 0,
+            cancelled: 0,
+            disputed: 0,
+            disputes_lost: 0,
+            total_fulfill_time: 0,
+            avg_fulfill_time: 0,
+        },
+    }
+}
+
+// Apply `update` to a seller's reputation record and persist it
+fn update_reputation(env: &Env, seller: &Address, update: impl FnOnce(&mut SellerReputation)) {
+    let mut record = read_reputation(env, seller);
+    update(&mut record);
+    let key = OrderKey::Reputation(seller.clone());
+    env.storage().persistent().set(&key, &record);
+    bump_persistent(env, &key);
+}
+
+fn load_dispute(env: &Env, order_id: u64) -> Result<Dispute, OrderError> {
+    let key = OrderKey::Dispute(order_id);
+    let dispute: Dispute = env
+        .storage()
+        .persistent()
+        .get(&key)
+        .ok_or(OrderError::NotFound)?;
+    bump_persistent(env, &key);
+    Ok(dispute)
+}
+
+fn save_dispute(env: &Env, order_id: u64, dispute: &Dispute) {
+    let key = OrderKey::Dispute(order_id);
+    env.storage().persistent().set(&key, dispute);
+    bump_persistent(env, &key);
+}
+
+// Publish an order state change under ("order", action, order_id, buyer)
+fn publish_order_event(env: &Env, action: Symbol, order: &Order, actor: &Address) {
+    env.events().publish(
+        (
+            symbol_short!("order"),
+            action,
+            order.order_id,
+            order.buyer.clone(),
+        ),
+        OrderEvent {
+            version: EVENT_VERSION,
+            actor: actor.clone(),
+            timestamp: env.ledger().timestamp(),
+        },
+    );
+}
+
+mod test;
@@ -1,21 +1,3072 @@
 #![cfg(test)]
+extern crate std;
 
 use super::*;
-use soroban_sdk::{vec, Env, String};
+use ed25519_dalek::SigningKey;
+use soroban_sdk::testutils::ed25519::Sign;
+use soroban_sdk::testutils::storage::Persistent as _;
+use soroban_sdk::testutils::{
+    Address as _, AuthorizedFunction, AuthorizedInvocation, Events, Ledger,
+};
+use soroban_sdk::token::{StellarAssetClient, TokenClient};
+use soroban_sdk::{
+    map, symbol_short, vec, Bytes, BytesN, Env, IntoVal, String, Symbol, TryFromVal, Val, Vec,
+};
+
+// Build of commit 9058223 (code version 1, schema version 1), the code upgrade tests
+// start from (make prev-wasm)
+#[allow(clippy::too_many_arguments)]
+mod contract_prev {
+    soroban_sdk::contractimport!(file = "test_wasms/hello_world_prev.wasm");
+}
+
+// Build of the current source, the version upgrade tests move to (make test-wasms)
+#[allow(clippy::too_many_arguments)]
+mod contract_next {
+    soroban_sdk::contractimport!(file = "test_wasms/hello_world_next.wasm");
+}
+
+const PRICE: i128 = 100;
+const BUYER_FUNDS: i128 = 1_000_000;
+
+struct Setup {
+    env: Env,
+    client: OrderFulfillmentVerifierClient<'static>,
+    admin: Address,
+    fulfiller: Address,
+    arbiter: Address,
+    buyer: Address,
+    seller: Address,
+    token: Address,
+}
+
+fn default_config() -> Config {
+    Config {
+        fulfillment_deadline: 7 * 24 * 60 * 60,
+        max_product_len: 64,
+        max_items: 10,
+        max_quantity: 1_000,
+        dispute_window: 3 * 24 * 60 * 60,
+        fee_bps: 0,
+    }
+}
+
+fn setup() -> Setup {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let contract_id = env.register(OrderFulfillmentVerifier, (&admin, default_config()));
+    let client = OrderFulfillmentVerifierClient::new(&env, &contract_id);
+
+    let fulfiller = Address::generate(&env);
+    client.add_fulfiller(&fulfiller);
+    let arbiter = Address::generate(&env);
+    client.add_arbiter(&arbiter);
+    let buyer = Address::generate(&env);
+    let seller = Address::generate(&env);
+    client.register_seller(&seller, &String::from_str(&env, "Acme Supplies"));
+    client.approve_seller(&seller);
+
+    let token = env
+        .register_stellar_asset_contract_v2(Address::generate(&env))
+        .address();
+    StellarAssetClient::new(&env, &token).mint(&buyer, &BUYER_FUNDS);
+
+    Setup {
+        env,
+        client,
+        admin,
+        fulfiller,
+        arbiter,
+        buyer,
+        seller,
+        token,
+    }
+}
+
+fn new_order(s: &Setup) -> u64 {
+    s.client.create_order(
+        &s.buyer,
+        &s.seller,
+        &String::from_str(&s.env, "Laptop"),
+        &s.token,
+        &PRICE,
+    )
+}
+
+fn balance(s: &Setup, id: &Address) -> i128 {
+    TokenClient::new(&s.env, &s.token).balance(id)
+}
+
+// Events published by the order contract itself, leaving out token transfers
+fn contract_events(s: &Setup) -> Vec<(Address, Vec<Val>, Val)> {
+    let mut events = Vec::new(&s.env);
+    for event in s.env.events().all().iter() {
+        if event.0 == s.client.address {
+            events.push_back(event);
+        }
+    }
+    events
+}
+
+fn order_ttl(s: &Setup, order_id: u64) -> u32 {
+    entry_ttl(s, &OrderKey::Order(order_id))
+}
+
+fn entry_ttl(s: &Setup, key: &OrderKey) -> u32 {
+    s.env.as_contract(&s.client.address, || {
+        s.env.storage().persistent().get_ttl(key)
+    })
+}
+
+#[test]
+fn test_create_and_fulfill_order() {
+    let s = setup();
+
+    let order_id = new_order(&s);
+    assert_eq!(order_id, 1);
+
+    let order = s.client.get_order(&order_id);
+    assert_eq!(order.buyer, s.buyer);
+    assert_eq!(order.status, OrderStatus::Created);
+
+    s.client.fulfill_order(&s.fulfiller, &order_id, &None);
+    assert_eq!(s.client.get_order(&order_id).status, OrderStatus::Delivered);
+}
+
+#[test]
+fn test_fulfill_twice_fails() {
+    let s = setup();
+
+    let order_id = new_order(&s);
+    s.client.fulfill_order(&s.fulfiller, &order_id, &None);
+    assert_eq!(
+        s.client.try_fulfill_order(&s.fulfiller, &order_id, &None),
+        Err(Ok(OrderError::AlreadyFulfilled))
+    );
+}
+
+#[test]
+fn test_get_unknown_order() {
+    let s = setup();
+
+    assert_eq!(s.client.try_get_order(&7), Err(Ok(OrderError::NotFound)));
+    assert_eq!(
+        s.client.try_fulfill_order(&s.fulfiller, &7, &None),
+        Err(Ok(OrderError::NotFound))
+    );
+}
+
+#[test]
+fn test_seller_registry() {
+    let s = setup();
+    let seller = Address::generate(&s.env);
+    assert_eq!(
+        s.client.try_get_seller(&seller),
+        Err(Ok(OrderError::SellerNotFound))
+    );
+
+    s.env.ledger().with_mut(|li| li.timestamp = 10);
+    s.client
+        .register_seller(&seller, &String::from_str(&s.env, "Widgets Co"));
+    assert_eq!(s.env.auths()[0].0, seller);
+    assert_eq!(
+        s.client.get_seller(&seller),
+        SellerProfile {
+            metadata: String::from_str(&s.env, "Widgets Co"),
+            status: SellerStatus::Pending,
+            registered_at: 10,
+        }
+    );
+
+    s.client.approve_seller(&seller);
+    assert_eq!(s.env.auths()[0].0, s.admin);
+    assert_eq!(s.client.get_seller(&seller).status, SellerStatus::Approved);
+
+    // Updating the profile keeps the seller's standing
+    s.client
+        .register_seller(&seller, &String::from_str(&s.env, "Widgets Ltd"));
+    let profile = s.client.get_seller(&seller);
+    assert_eq!(profile.metadata, String::from_str(&s.env, "Widgets Ltd"));
+    assert_eq!(profile.status, SellerStatus::Approved);
+    assert_eq!(profile.registered_at, 10);
+
+    s.client.suspend_seller(&seller);
+    assert_eq!(s.env.auths()[0].0, s.admin);
+    assert_eq!(s.client.get_seller(&seller).status, SellerStatus::Suspended);
+}
+
+#[test]
+fn test_seller_status_changes_require_admin() {
+    let s = setup();
+    assert_eq!(
+        s.client.try_approve_seller(&Address::generate(&s.env)),
+        Err(Ok(OrderError::SellerNotFound))
+    );
+
+    s.env.set_auths(&[]);
+    assert!(s.client.try_suspend_seller(&s.seller).is_err());
+    assert_eq!(
+        s.client.get_seller(&s.seller).status,
+        SellerStatus::Approved
+    );
+}
+
+#[test]
+fn test_register_seller_rejects_long_metadata() {
+    let s = setup();
+    let metadata = String::from_bytes(&s.env, &[b'x'; MAX_SELLER_METADATA_LEN as usize + 1]);
+
+    assert_eq!(
+        s.client
+            .try_register_seller(&Address::generate(&s.env), &metadata),
+        Err(Ok(OrderError::MetadataTooLong))
+    );
+}
+
+#[test]
+fn test_create_order_rejects_unusable_sellers() {
+    let s = setup();
+    let product = String::from_str(&s.env, "Laptop");
+    let seller = Address::generate(&s.env);
+
+    assert_eq!(
+        s.client
+            .try_create_order(&s.buyer, &seller, &product, &s.token, &PRICE),
+        Err(Ok(OrderError::SellerNotFound))
+    );
+
+    s.client
+        .register_seller(&seller, &String::from_str(&s.env, "Widgets Co"));
+    assert_eq!(
+        s.client
+            .try_create_order(&s.buyer, &seller, &product, &s.token, &PRICE),
+        Err(Ok(OrderError::SellerNotApproved))
+    );
+
+    s.client.approve_seller(&seller);
+    let order_id = s
+        .client
+        .create_order(&s.buyer, &seller, &product, &s.token, &PRICE);
+    assert_eq!(s.client.get_order(&order_id).seller, seller);
+
+    s.client.suspend_seller(&seller);
+    assert_eq!(
+        s.client
+            .try_create_order(&s.buyer, &seller, &product, &s.token, &PRICE),
+        Err(Ok(OrderError::SellerNotApproved))
+    );
+}
+
+fn order_ids(orders: &Vec<Order>) -> std::vec::Vec<u64> {
+    orders.iter().map(|order| order.order_id).collect()
+}
+
+#[test]
+fn test_list_orders_by_buyer_pages() {
+    let s = setup();
+    let other_buyer = Address::generate(&s.env);
+    StellarAssetClient::new(&s.env, &s.token).mint(&other_buyer, &BUYER_FUNDS);
+    let product = String::from_str(&s.env, "Laptop");
+
+    for _ in 0..5 {
+        new_order(&s);
+        s.client
+            .create_order(&other_buyer, &s.seller, &product, &s.token, &PRICE);
+    }
+
+    let (page, cursor) = s.client.list_orders_by_buyer(&s.buyer, &None, &2);
+    assert_eq!(order_ids(&page), [1, 3]);
+    assert_eq!(cursor, Some(2));
+
+    let (page, cursor) = s.client.list_orders_by_buyer(&s.buyer, &cursor, &2);
+    assert_eq!(order_ids(&page), [5, 7]);
+    assert_eq!(cursor, Some(4));
+
+    let (page, cursor) = s.client.list_orders_by_buyer(&s.buyer, &cursor, &2);
+    assert_eq!(order_ids(&page), [9]);
+    assert_eq!(cursor, None);
+    assert!(page.iter().all(|order| order.buyer == s.buyer));
+
+    let (page, cursor) = s.client.list_orders_by_buyer(&other_buyer, &Some(4), &10);
+    assert_eq!(order_ids(&page), [10]);
+    assert_eq!(cursor, None);
+}
+
+#[test]
+fn test_list_orders_by_buyer_edge_cases() {
+    let s = setup();
+
+    let (page, cursor) = s.client.list_orders_by_buyer(&s.buyer, &None, &5);
+    assert!(page.is_empty());
+    assert_eq!(cursor, None);
+
+    new_order(&s);
+    let (page, cursor) = s.client.list_orders_by_buyer(&s.buyer, &Some(7), &5);
+    assert!(page.is_empty());
+    assert_eq!(cursor, None);
+
+    assert_eq!(
+        s.client.try_list_orders_by_buyer(&s.buyer, &None, &0),
+        Err(Ok(OrderError::InvalidLimit))
+    );
+}
+
+#[test]
+fn test_list_orders_by_buyer_caps_page_size() {
+    let s = setup();
+    for _ in 0..MAX_PAGE_SIZE + 3 {
+        new_order(&s);
+    }
+
+    let (page, cursor) = s.client.list_orders_by_buyer(&s.buyer, &None, &u32::MAX);
+    assert_eq!(page.len(), MAX_PAGE_SIZE);
+    assert_eq!(cursor, Some(MAX_PAGE_SIZE));
+
+    // A full page stays within the network's read entry limit
+    let resources = s.env.cost_estimate().resources();
+    assert!(resources.read_entries <= 40, "{}", resources.read_entries);
+}
+
+fn seller_queue(s: &Setup, status: OrderStatus) -> std::vec::Vec<u64> {
+    let (page, cursor) = s
+        .client
+        .list_orders_by_seller(&s.seller, &status, &None, &MAX_PAGE_SIZE);
+    assert_eq!(cursor, None);
+    order_ids(&page)
+}
+
+#[test]
+fn test_seller_status_queues_follow_transitions() {
+    let s = setup();
+    for _ in 0..5 {
+        new_order(&s);
+    }
+    assert_eq!(seller_queue(&s, OrderStatus::Created), [1, 2, 3, 4, 5]);
+
+    // Leaving from the middle, the head and the tail keeps the rest in FIFO order
+    s.client.accept_order(&3);
+    s.client.accept_order(&1);
+    s.client.accept_order(&5);
+    assert_eq!(seller_queue(&s, OrderStatus::Created), [2, 4]);
+    assert_eq!(seller_queue(&s, OrderStatus::Accepted), [3, 1, 5]);
+
+    s.client.ship_order(&s.fulfiller, &1);
+    s.client.cancel_order(&2);
+    s.client.cancel_order(&4);
+    assert!(seller_queue(&s, OrderStatus::Created).is_empty());
+    assert_eq!(seller_queue(&s, OrderStatus::Accepted), [3, 5]);
+    assert_eq!(seller_queue(&s, OrderStatus::Shipped), [1]);
+    assert_eq!(seller_queue(&s, OrderStatus::Cancelled), [2, 4]);
+
+    // A fresh order after the queue emptied starts it again
+    new_order(&s);
+    assert_eq!(seller_queue(&s, OrderStatus::Created), [6]);
+}
+
+#[test]
+fn test_list_orders_by_seller_pages() {
+    let s = setup();
+    let other_seller = Address::generate(&s.env);
+    s.client
+        .register_seller(&other_seller, &String::from_str(&s.env, "Other"));
+    s.client.approve_seller(&other_seller);
+    let product = String::from_str(&s.env, "Laptop");
+
+    for _ in 0..5 {
+        new_order(&s);
+        s.client
+            .create_order(&s.buyer, &other_seller, &product, &s.token, &PRICE);
+    }
+
+    let created = OrderStatus::Created;
+    let (page, cursor) = s
+        .client
+        .list_orders_by_seller(&s.seller, &created, &None, &2);
+    assert_eq!(order_ids(&page), [1, 3]);
+    assert_eq!(cursor, Some(5));
+
+    let (page, cursor) = s
+        .client
+        .list_orders_by_seller(&s.seller, &created, &cursor, &2);
+    assert_eq!(order_ids(&page), [5, 7]);
+    assert_eq!(cursor, Some(9));
+
+    let (page, cursor) = s
+        .client
+        .list_orders_by_seller(&s.seller, &created, &cursor, &2);
+    assert_eq!(order_ids(&page), [9]);
+    assert_eq!(cursor, None);
+
+    let (page, _) = s
+        .client
+        .list_orders_by_seller(&other_seller, &created, &None, &10);
+    assert_eq!(order_ids(&page), [2, 4, 6, 8, 10]);
+}
+
+#[test]
+fn test_list_orders_by_seller_rejects_stale_cursor() {
+    let s = setup();
+    for _ in 0..3 {
+        new_order(&s);
+    }
+    let created = OrderStatus::Created;
+
+    let (_, cursor) = s
+        .client
+        .list_orders_by_seller(&s.seller, &created, &None, &1);
+    assert_eq!(cursor, Some(2));
+    s.client.accept_order(&2);
+    assert_eq!(
+        s.client
+            .try_list_orders_by_seller(&s.seller, &created, &cursor, &1),
+        Err(Ok(OrderError::InvalidCursor))
+    );
+    assert_eq!(
+        s.client
+            .try_list_orders_by_seller(&s.seller, &created, &None, &0),
+        Err(Ok(OrderError::InvalidLimit))
+    );
+}
+
+#[test]
+fn test_next_pending_order() {
+    let s = setup();
+    assert_eq!(s.client.next_pending_order(&s.seller), None);
+
+    new_order(&s);
+    new_order(&s);
+    assert_eq!(s.client.next_pending_order(&s.seller).unwrap().order_id, 1);
+
+    s.client.accept_order(&1);
+    assert_eq!(s.client.next_pending_order(&s.seller).unwrap().order_id, 2);
+
+    s.client.cancel_order(&2);
+    assert_eq!(s.client.next_pending_order(&s.seller), None);
+}
+
+fn new_order_with_code(s: &Setup, code: &Bytes) -> u64 {
+    let delivery_hash = s.env.crypto().sha256(code).to_bytes();
+    s.client.create_order_with_items(
+        &s.buyer,
+        &s.seller,
+        &vec![&s.env, item(s, "Laptop", 1, PRICE)],
+        &s.token,
+        &Some(delivery_hash),
+        &None,
+    )
+}
+
+#[test]
+fn test_fulfill_with_delivery_code() {
+    let s = setup();
+    let code = Bytes::from_slice(&s.env, b"483920");
+
+    let order_id = new_order_with_code(&s, &code);
+    assert_eq!(
+        s.client.get_order(&order_id).delivery_hash,
+        Some(s.env.crypto().sha256(&code).to_bytes().into())
+    );
+
+    s.client.fulfill_order(&s.fulfiller, &order_id, &Some(code));
+    assert_eq!(s.client.get_order(&order_id).status, OrderStatus::Delivered);
+}
+
+#[test]
+fn test_fulfill_rejects_missing_or_wrong_delivery_code() {
+    let s = setup();
+    let order_id = new_order_with_code(&s, &Bytes::from_slice(&s.env, b"483920"));
+
+    assert_eq!(
+        s.client.try_fulfill_order(&s.fulfiller, &order_id, &None),
+        Err(Ok(OrderError::InvalidDeliveryCode))
+    );
+    assert_eq!(
+        s.client.try_fulfill_order(
+            &s.fulfiller,
+            &order_id,
+            &Some(Bytes::from_slice(&s.env, b"000000"))
+        ),
+        Err(Ok(OrderError::InvalidDeliveryCode))
+    );
+    assert_eq!(s.client.get_order(&order_id).status, OrderStatus::Created);
+    assert_eq!(balance(&s, &s.client.address), PRICE);
+}
+
+#[test]
+fn test_delivery_code_ignored_without_commitment() {
+    let s = setup();
+    let order_id = new_order(&s);
+
+    s.client.fulfill_order(
+        &s.fulfiller,
+        &order_id,
+        &Some(Bytes::from_slice(&s.env, b"anything")),
+    );
+    assert_eq!(s.client.get_order(&order_id).status, OrderStatus::Delivered);
+}
+
+fn attester_key(s: &Setup, seed: u8) -> (SigningKey, BytesN<32>) {
+    let signing_key = SigningKey::from_bytes(&[seed; 32]);
+    let public_key = BytesN::from_array(&s.env, &signing_key.verifying_key().to_bytes());
+    (signing_key, public_key)
+}
+
+fn attestation(s: &Setup, order_id: u64, attester: &BytesN<32>, nonce: u64) -> DeliveryAttestation {
+    DeliveryAttestation {
+        contract: s.client.address.clone(),
+        order_id,
+        attester: attester.clone(),
+        timestamp: s.env.ledger().timestamp(),
+        tracking_hash: BytesN::from_array(&s.env, &[9; 32]),
+        nonce,
+    }
+}
+
+fn sign(s: &Setup, key: &SigningKey, payload: &DeliveryAttestation) -> BytesN<64> {
+    BytesN::from_array(&s.env, &Sign::sign(key, payload).unwrap())
+}
+
+#[test]
+fn test_attester_registry() {
+    let s = setup();
+    let (_, public_key) = attester_key(&s, 1);
+    assert!(!s.client.is_attester(&public_key));
+
+    s.client.add_attester(&public_key);
+    assert_eq!(s.env.auths()[0].0, s.admin);
+    assert!(s.client.is_attester(&public_key));
+
+    s.client.remove_attester(&public_key);
+    assert_eq!(s.env.auths()[0].0, s.admin);
+    assert!(!s.client.is_attester(&public_key));
+}
+
+#[test]
+fn test_fulfill_with_attestation() {
+    let s = setup();
+    let (signing_key, public_key) = attester_key(&s, 1);
+    s.client.add_attester(&public_key);
+    s.env.ledger().with_mut(|li| li.timestamp = 1_000);
+
+    let order_id = new_order(&s);
+    let payload = attestation(&s, order_id, &public_key, 1);
+    let signature = sign(&s, &signing_key, &payload);
+    s.client
+        .fulfill_with_attestation(&order_id, &payload, &signature);
+
+    let order = s.client.get_order(&order_id);
+    assert_eq!(order.status, OrderStatus::Delivered);
+    assert_eq!(order.attester, Some(public_key.into()));
+}
+
+#[test]
+fn test_fulfill_with_attestation_rejects_bad_payloads() {
+    let s = setup();
+    let (signing_key, public_key) = attester_key(&s, 1);
+    let (rogue_key, rogue_public_key) = attester_key(&s, 2);
+    s.client.add_attester(&public_key);
+    let order_id = new_order(&s);
+
+    let payload = attestation(&s, order_id, &rogue_public_key, 1);
+    let signature = sign(&s, &rogue_key, &payload);
+    assert_eq!(
+        s.client
+            .try_fulfill_with_attestation(&order_id, &payload, &signature),
+        Err(Ok(OrderError::Unauthorized))
+    );
+
+    let payload = attestation(&s, order_id + 1, &public_key, 1);
+    let signature = sign(&s, &signing_key, &payload);
+    assert_eq!(
+        s.client
+            .try_fulfill_with_attestation(&order_id, &payload, &signature),
+        Err(Ok(OrderError::InvalidAttestation))
+    );
+
+    let mut payload = attestation(&s, order_id, &public_key, 1);
+    payload.timestamp += 1;
+    let signature = sign(&s, &signing_key, &payload);
+    assert_eq!(
+        s.client
+            .try_fulfill_with_attestation(&order_id, &payload, &signature),
+        Err(Ok(OrderError::InvalidAttestation))
+    );
+
+    // Signed for another deployment of the verifier
+    let mut payload = attestation(&s, order_id, &public_key, 1);
+    payload.contract = Address::generate(&s.env);
+    let signature = sign(&s, &signing_key, &payload);
+    assert_eq!(
+        s.client
+            .try_fulfill_with_attestation(&order_id, &payload, &signature),
+        Err(Ok(OrderError::InvalidAttestation))
+    );
+
+    // A signature by another key, or over different data, fails verification
+    let payload = attestation(&s, order_id, &public_key, 1);
+    let forged = sign(&s, &rogue_key, &payload);
+    assert!(s
+        .client
+        .try_fulfill_with_attestation(&order_id, &payload, &forged)
+        .is_err());
+    let mut tampered = payload.clone();
+    tampered.tracking_hash = BytesN::from_array(&s.env, &[1; 32]);
+    let signature = sign(&s, &signing_key, &payload);
+    assert!(s
+        .client
+        .try_fulfill_with_attestation(&order_id, &tampered, &signature)
+        .is_err());
+
+    assert_eq!(s.client.get_order(&order_id).status, OrderStatus::Created);
+}
+
+#[test]
+fn test_fulfill_with_attestation_rejects_order_with_delivery_code() {
+    let s = setup();
+    let (signing_key, public_key) = attester_key(&s, 1);
+    s.client.add_attester(&public_key);
+    let code = Bytes::from_slice(&s.env, b"483920");
+    let order_id = new_order_with_code(&s, &code);
+
+    let payload = attestation(&s, order_id, &public_key, 1);
+    let signature = sign(&s, &signing_key, &payload);
+    assert_eq!(
+        s.client
+            .try_fulfill_with_attestation(&order_id, &payload, &signature),
+        Err(Ok(OrderError::InvalidDeliveryCode))
+    );
+    assert_eq!(s.client.get_order(&order_id).status, OrderStatus::Created);
+
+    s.client.fulfill_order(&s.fulfiller, &order_id, &Some(code));
+    assert_eq!(s.client.get_order(&order_id).status, OrderStatus::Delivered);
+}
+
+#[test]
+fn test_fulfill_with_attestation_rejects_replayed_nonce() {
+    let s = setup();
+    let (signing_key, public_key) = attester_key(&s, 1);
+    s.client.add_attester(&public_key);
+    let first = new_order(&s);
+    let second = new_order(&s);
+
+    let payload = attestation(&s, first, &public_key, 7);
+    let signature = sign(&s, &signing_key, &payload);
+    s.client
+        .fulfill_with_attestation(&first, &payload, &signature);
+
+    let payload = attestation(&s, second, &public_key, 7);
+    let signature = sign(&s, &signing_key, &payload);
+    assert_eq!(
+        s.client
+            .try_fulfill_with_attestation(&second, &payload, &signature),
+        Err(Ok(OrderError::AttestationReplayed))
+    );
+
+    let payload = attestation(&s, second, &public_key, 8);
+    let signature = sign(&s, &signing_key, &payload);
+    s.client
+        .fulfill_with_attestation(&second, &payload, &signature);
+    assert_eq!(s.client.get_order(&second).status, OrderStatus::Delivered);
+}
+
+#[test]
+fn test_create_order_locks_escrow() {
+    let s = setup();
+
+    let order_id = new_order(&s);
+    let order = s.client.get_order(&order_id);
+    assert_eq!(order.token, s.token);
+    assert_eq!(order.amount, PRICE);
+    assert_eq!(balance(&s, &s.buyer), BUYER_FUNDS - PRICE);
+    assert_eq!(balance(&s, &s.client.address), PRICE);
+}
+
+fn item(s: &Setup, sku: &str, quantity: u32, unit_price: i128) -> LineItem {
+    LineItem {
+        sku: String::from_str(&s.env, sku),
+        quantity,
+        unit_price,
+    }
+}
+
+#[test]
+fn test_single_product_order_is_one_line_item() {
+    let s = setup();
+
+    let order_id = new_order(&s);
+    assert_eq!(
+        s.client.get_order(&order_id).items,
+        vec![&s.env, item(&s, "Laptop", 1, PRICE)]
+    );
+}
+
+#[test]
+fn test_create_order_with_items_computes_total() {
+    let s = setup();
+    let items = vec![
+        &s.env,
+        item(&s, "SKU-1", 3, 25),
+        item(&s, "SKU-2", 1, 40),
+        item(&s, "SKU-3", 10, 2),
+    ];
+
+    let order_id = s
+        .client
+        .create_order_with_items(&s.buyer, &s.seller, &items, &s.token, &None, &None);
+    let order = s.client.get_order(&order_id);
+    assert_eq!(order.items, items);
+    assert_eq!(order.amount, 135);
+    assert_eq!(balance(&s, &s.client.address), 135);
+    assert_eq!(balance(&s, &s.buyer), BUYER_FUNDS - 135);
+}
+
+#[test]
+fn test_create_order_with_items_validation() {
+    let s = setup();
+    let config = default_config();
+    let create = |items: Vec<LineItem>| {
+        s.client
+            .try_create_order_with_items(&s.buyer, &s.seller, &items, &s.token, &None, &None)
+    };
+
+    assert_eq!(create(vec![&s.env]), Err(Ok(OrderError::EmptyOrder)));
+
+    let mut too_many = Vec::new(&s.env);
+    for _ in 0..=config.max_items {
+        too_many.push_back(item(&s, "SKU-1", 1, 1));
+    }
+    assert_eq!(create(too_many), Err(Ok(OrderError::TooManyItems)));
+
+    assert_eq!(
+        create(vec![&s.env, item(&s, "SKU-1", 0, 1)]),
+        Err(Ok(OrderError::InvalidQuantity))
+    );
+    assert_eq!(
+        create(vec![&s.env, item(&s, "SKU-1", config.max_quantity + 1, 1)]),
+        Err(Ok(OrderError::InvalidQuantity))
+    );
+    assert_eq!(
+        create(vec![
+            &s.env,
+            item(&s, "SKU-1", 1, 5),
+            item(&s, "SKU-2", 1, 0)
+        ]),
+        Err(Ok(OrderError::InvalidAmount))
+    );
+    let long_sku = "S".repeat(config.max_product_len as usize + 1);
+    assert_eq!(
+        create(vec![&s.env, item(&s, &long_sku, 1, 1)]),
+        Err(Ok(OrderError::ProductTooLong))
+    );
+    assert_eq!(balance(&s, &s.buyer), BUYER_FUNDS);
+}
+
+#[test]
+fn test_create_order_with_items_overflow() {
+    let s = setup();
+
+    assert_eq!(
+        s.client.try_create_order_with_items(
+            &s.buyer,
+            &s.seller,
+            &vec![&s.env, item(&s, "SKU-1", 2, i128::MAX / 2 + 1)],
+            &s.token,
+            &None,
+            &None,
+        ),
+        Err(Ok(OrderError::Overflow))
+    );
+    assert_eq!(
+        s.client.try_create_order_with_items(
+            &s.buyer,
+            &s.seller,
+            &vec![
+                &s.env,
+                item(&s, "SKU-1", 1, i128::MAX),
+                item(&s, "SKU-2", 1, 1)
+            ],
+            &s.token,
+            &None,
+            &None,
+        ),
+        Err(Ok(OrderError::Overflow))
+    );
+}
+
+fn new_multi_item_order(s: &Setup) -> u64 {
+    let items = vec![
+        &s.env,
+        item(s, "SKU-1", 3, 25),
+        item(s, "SKU-2", 1, 40),
+        item(s, "SKU-3", 10, 2),
+    ];
+    s.client
+        .create_order_with_items(&s.buyer, &s.seller, &items, &s.token, &None, &None)
+}
+
+#[test]
+fn test_fulfill_items_in_parcels() {
+    let s = setup();
+    let order_id = new_multi_item_order(&s);
+    assert_eq!(
+        s.client.get_order(&order_id).fulfilled,
+        vec![&s.env, 0, 0, 0]
+    );
+
+    s.client
+        .fulfill_items(&s.fulfiller, &order_id, &vec![&s.env, (0, 2)], &None);
+    assert_eq!(s.env.auths()[0].0, s.fulfiller);
+    let order = s.client.get_order(&order_id);
+    assert_eq!(order.status, OrderStatus::PartiallyFulfilled);
+    assert_eq!(order.fulfilled, vec![&s.env, 2, 0, 0]);
+    assert_eq!(order.released, 50);
+    assert_eq!(balance(&s, &s.seller), 50);
+
+    s.client.fulfill_items(
+        &s.fulfiller,
+        &order_id,
+        &vec![&s.env, (0, 1), (2, 4)],
+        &None,
+    );
+    let order = s.client.get_order(&order_id);
+    assert_eq!(order.status, OrderStatus::PartiallyFulfilled);
+    assert_eq!(order.fulfilled, vec![&s.env, 3, 0, 4]);
+    assert_eq!(balance(&s, &s.seller), 83);
+
+    // The last parcel delivers the order, its value stays in escrow until completion
+    s.client.fulfill_items(
+        &s.fulfiller,
+        &order_id,
+        &vec![&s.env, (1, 1), (2, 6)],
+        &None,
+    );
+    let order = s.client.get_order(&order_id);
+    assert_eq!(order.status, OrderStatus::Delivered);
+    assert_eq!(order.fulfilled, vec![&s.env, 3, 1, 10]);
+    assert_eq!(balance(&s, &s.seller), 83);
+    assert_eq!(balance(&s, &s.client.address), 52);
+
+    s.client.complete_order(&order_id);
+    assert_eq!(balance(&s, &s.seller), 135);
+    assert_eq!(balance(&s, &s.client.address), 0);
+}
+
+#[test]
+fn test_fulfill_items_validation() {
+    let s = setup();
+    let order_id = new_multi_item_order(&s);
+    let fulfill = |items: Vec<(u32, u32)>| {
+        s.client
+            .try_fulfill_items(&s.fulfiller, &order_id, &items, &None)
+    };
+
+    assert_eq!(fulfill(vec![&s.env]), Err(Ok(OrderError::EmptyOrder)));
+    assert_eq!(
+        fulfill(vec![&s.env, (3, 1)]),
+        Err(Ok(OrderError::InvalidLineIndex))
+    );
+    assert_eq!(
+        fulfill(vec![&s.env, (0, 0)]),
+        Err(Ok(OrderError::InvalidQuantity))
+    );
+    assert_eq!(
+        fulfill(vec![&s.env, (1, 2)]),
+        Err(Ok(OrderError::InvalidQuantity))
+    );
+    assert_eq!(
+        fulfill(vec![&s.env, (0, 2), (0, 2)]),
+        Err(Ok(OrderError::InvalidQuantity))
+    );
+    assert_eq!(
+        s.client.try_fulfill_items(
+            &Address::generate(&s.env),
+            &order_id,
+            &vec![&s.env, (0, 1)],
+            &None
+        ),
+        Err(Ok(OrderError::Unauthorized))
+    );
+
+    let order = s.client.get_order(&order_id);
+    assert_eq!(order.status, OrderStatus::Created);
+    assert_eq!(order.fulfilled, vec![&s.env, 0, 0, 0]);
+    assert_eq!(balance(&s, &s.seller), 0);
+}
+
+#[test]
+fn test_fulfill_items_needs_delivery_code_on_every_parcel() {
+    let s = setup();
+    let code = Bytes::from_slice(&s.env, b"483920");
+    let order_id = s.client.create_order_with_items(
+        &s.buyer,
+        &s.seller,
+        &vec![&s.env, item(&s, "SKU-1", 2, 25)],
+        &s.token,
+        &Some(s.env.crypto().sha256(&code).to_bytes()),
+        &None,
+    );
+
+    // A partial parcel pays the seller, so it can't go out without the code either
+    for delivery_code in [None, Some(Bytes::from_slice(&s.env, b"000000"))] {
+        assert_eq!(
+            s.client.try_fulfill_items(
+                &s.fulfiller,
+                &order_id,
+                &vec![&s.env, (0, 1)],
+                &delivery_code
+            ),
+            Err(Ok(OrderError::InvalidDeliveryCode))
+        );
+    }
+    assert_eq!(s.client.get_order(&order_id).status, OrderStatus::Created);
+    assert_eq!(balance(&s, &s.seller), 0);
+
+    s.client.fulfill_items(
+        &s.fulfiller,
+        &order_id,
+        &vec![&s.env, (0, 1)],
+        &Some(code.clone()),
+    );
+    assert_eq!(balance(&s, &s.seller), 25);
+    assert_eq!(
+        s.client
+            .try_fulfill_items(&s.fulfiller, &order_id, &vec![&s.env, (0, 1)], &None),
+        Err(Ok(OrderError::InvalidDeliveryCode))
+    );
+    s.client
+        .fulfill_items(&s.fulfiller, &order_id, &vec![&s.env, (0, 1)], &Some(code));
+    assert_eq!(s.client.get_order(&order_id).status, OrderStatus::Delivered);
+}
+
+#[test]
+fn test_partially_fulfilled_order_settles_remaining_escrow() {
+    let s = setup();
+    let refunded = new_multi_item_order(&s);
+    let delivered = new_multi_item_order(&s);
+    let disputed = new_multi_item_order(&s);
+    for order_id in [refunded, delivered, disputed] {
+        s.client
+            .fulfill_items(&s.fulfiller, &order_id, &vec![&s.env, (1, 1)], &None);
+    }
+    assert_eq!(balance(&s, &s.seller), 120);
+    assert_eq!(
+        s.client.try_cancel_order(&refunded),
+        Err(Ok(OrderError::InvalidState))
+    );
+
+    // Delivering the rest in one go marks every unit fulfilled
+    s.client.fulfill_order(&s.fulfiller, &delivered, &None);
+    assert_eq!(
+        s.client.get_order(&delivered).fulfilled,
+        vec![&s.env, 3, 1, 10]
+    );
+    s.client.fulfill_order(&s.fulfiller, &disputed, &None);
+    s.client.open_dispute(&disputed, &reason(&s));
+
+    // A ruling or a refund only covers what is still in escrow
+    s.client
+        .resolve_dispute(&s.arbiter, &disputed, &Ruling::Split(5_000));
+    assert_eq!(balance(&s, &s.seller), 120 + 47);
+
+    let deadline = s.client.get_order(&refunded).deadline;
+    s.env.ledger().with_mut(|li| li.timestamp = deadline + 1);
+    s.client.claim_refund(&refunded);
+    assert_eq!(s.client.get_order(&refunded).status, OrderStatus::Refunded);
+    assert_eq!(balance(&s, &s.buyer), BUYER_FUNDS - 3 * 135 + 95 + 48);
+    assert_eq!(balance(&s, &s.client.address), 95);
+}
+
+#[test]
+fn test_create_order_rejects_invalid_amount() {
+    let s = setup();
+    let product = String::from_str(&s.env, "Laptop");
+
+    for amount in [0, -1] {
+        assert_eq!(
+            s.client
+                .try_create_order(&s.buyer, &s.seller, &product, &s.token, &amount),
+            Err(Ok(OrderError::InvalidAmount))
+        );
+    }
+    assert_eq!(balance(&s, &s.buyer), BUYER_FUNDS);
+}
+
+#[test]
+fn test_create_order_with_insufficient_funds_fails() {
+    let s = setup();
+    let product = String::from_str(&s.env, "Laptop");
+
+    let res =
+        s.client
+            .try_create_order(&s.buyer, &s.seller, &product, &s.token, &(BUYER_FUNDS + 1));
+    assert_eq!(res, Err(Ok(OrderError::InsufficientFunds)));
+    assert_eq!(
+        s.client.try_create_order_with_items(
+            &s.buyer,
+            &s.seller,
+            &vec![&s.env, item(&s, "Laptop", 2, BUYER_FUNDS / 2 + 1)],
+            &s.token,
+            &None,
+            &None,
+        ),
+        Err(Ok(OrderError::InsufficientFunds))
+    );
+    assert_eq!(s.client.try_get_order(&1), Err(Ok(OrderError::NotFound)));
+    assert_eq!(balance(&s, &s.buyer), BUYER_FUNDS);
+}
+
+#[test]
+fn test_escrow_released_on_completion() {
+    let s = setup();
+    let order_id = new_order(&s);
+
+    s.client.fulfill_order(&s.fulfiller, &order_id, &None);
+    assert_eq!(balance(&s, &s.seller), 0);
+    assert_eq!(balance(&s, &s.client.address), PRICE);
+
+    s.client.complete_order(&order_id);
+    assert_eq!(balance(&s, &s.seller), PRICE);
+    assert_eq!(balance(&s, &s.client.address), 0);
+}
+
+#[test]
+fn test_cancel_order_refunds_escrow() {
+    let s = setup();
+    let order_id = new_order(&s);
+
+    s.client.cancel_order(&order_id);
+    assert_eq!(balance(&s, &s.buyer), BUYER_FUNDS);
+    assert_eq!(balance(&s, &s.client.address), 0);
+}
+
+#[test]
+fn test_cancel_order_only_before_acceptance() {
+    let s = setup();
+    let order_id = new_order(&s);
+    s.client.accept_order(&order_id);
+
+    assert_eq!(
+        s.client.try_cancel_order(&order_id),
+        Err(Ok(OrderError::InvalidState))
+    );
+    assert_eq!(s.client.get_order(&order_id).status, OrderStatus::Accepted);
+    assert_eq!(balance(&s, &s.client.address), PRICE);
+}
+
+#[test]
+fn test_reject_order() {
+    let s = setup();
+    let created = new_order(&s);
+    let accepted = new_order(&s);
+    s.client.accept_order(&accepted);
+
+    s.client.reject_order(&created, &1);
+    assert_eq!(s.env.auths()[0].0, s.seller);
+    s.client.reject_order(&accepted, &2);
+    for order_id in [created, accepted] {
+        assert_eq!(s.client.get_order(&order_id).status, OrderStatus::Cancelled);
+        assert_eq!(
+            s.client.try_fulfill_order(&s.fulfiller, &order_id, &None),
+            Err(Ok(OrderError::InvalidState))
+        );
+    }
+    assert_eq!(balance(&s, &s.buyer), BUYER_FUNDS);
+    assert_eq!(balance(&s, &s.client.address), 0);
+
+    // Once shipped, or already cancelled, the seller can no longer back out
+    let shipped = new_order(&s);
+    s.client.accept_order(&shipped);
+    s.client.ship_order(&s.fulfiller, &shipped);
+    for order_id in [created, shipped] {
+        assert_eq!(
+            s.client.try_reject_order(&order_id, &1),
+            Err(Ok(OrderError::InvalidState))
+        );
+    }
+
+    let pending = new_order(&s);
+    s.env.set_auths(&[]);
+    assert!(s.client.try_reject_order(&pending, &1).is_err());
+}
+
+#[test]
+fn test_reject_order_event() {
+    let s = setup();
+    s.env.ledger().with_mut(|li| li.timestamp = 1_000);
+    let order_id = new_order(&s);
+
+    s.client.reject_order(&order_id, &42);
+    assert_eq!(
+        contract_events(&s),
+        vec![
+            &s.env,
+            (
+                s.client.address.clone(),
+                (
+                    symbol_short!("order"),
+                    symbol_short!("rejected"),
+                    order_id,
+                    s.buyer.clone()
+                )
+                    .into_val(&s.env),
+                RejectionEvent {
+                    version: EVENT_VERSION,
+                    seller: s.seller.clone(),
+                    reason_code: 42,
+                    timestamp: 1_000,
+                }
+                .into_val(&s.env),
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_full_lifecycle_records_timestamps() {
+    let s = setup();
+    s.env.ledger().with_mut(|li| li.timestamp = 100);
+    let order_id = new_order(&s);
+
+    s.env.ledger().with_mut(|li| li.timestamp = 200);
+    s.client.accept_order(&order_id);
+    s.env.ledger().with_mut(|li| li.timestamp = 300);
+    s.client.ship_order(&s.fulfiller, &order_id);
+    s.env.ledger().with_mut(|li| li.timestamp = 400);
+    s.client.fulfill_order(&s.fulfiller, &order_id, &None);
+    s.env.ledger().with_mut(|li| li.timestamp = 500);
+    s.client.complete_order(&order_id);
+    assert_eq!(s.env.auths()[0].0, s.buyer);
+
+    let order = s.client.get_order(&order_id);
+    assert_eq!(order.status, OrderStatus::Completed);
+    assert_eq!(
+        order.status_times,
+        map![
+            &s.env,
+            (OrderStatus::Created, 100),
+            (OrderStatus::Accepted, 200),
+            (OrderStatus::Shipped, 300),
+            (OrderStatus::Delivered, 400),
+            (OrderStatus::Completed, 500)
+        ]
+    );
+}
+
+#[test]
+fn test_illegal_transitions_rejected() {
+    let s = setup();
+    let order_id = new_order(&s);
+
+    assert_eq!(
+        s.client.try_ship_order(&s.fulfiller, &order_id),
+        Err(Ok(OrderError::InvalidState))
+    );
+    assert_eq!(
+        s.client.try_complete_order(&order_id),
+        Err(Ok(OrderError::InvalidState))
+    );
+    assert_eq!(
+        s.client.try_open_dispute(&order_id, &reason(&s)),
+        Err(Ok(OrderError::InvalidState))
+    );
+    assert_eq!(
+        s.client
+            .try_resolve_dispute(&s.arbiter, &order_id, &Ruling::RefundBuyer),
+        Err(Ok(OrderError::InvalidState))
+    );
+
+    s.client.accept_order(&order_id);
+    assert_eq!(
+        s.client.try_accept_order(&order_id),
+        Err(Ok(OrderError::InvalidState))
+    );
+
+    s.client.ship_order(&s.fulfiller, &order_id);
+    assert_eq!(
+        s.client.try_cancel_order(&order_id),
+        Err(Ok(OrderError::InvalidState))
+    );
+    assert_eq!(s.client.get_order(&order_id).status, OrderStatus::Shipped);
+}
+
+#[test]
+fn test_cancel_order() {
+    let s = setup();
+    let order_id = new_order(&s);
+
+    s.client.cancel_order(&order_id);
+    assert_eq!(s.env.auths()[0].0, s.buyer);
+    assert_eq!(s.client.get_order(&order_id).status, OrderStatus::Cancelled);
+
+    assert_eq!(
+        s.client.try_fulfill_order(&s.fulfiller, &order_id, &None),
+        Err(Ok(OrderError::InvalidState))
+    );
+}
+
+fn reason(s: &Setup) -> BytesN<32> {
+    BytesN::from_array(&s.env, &[7; 32])
+}
+
+fn disputed_order(s: &Setup) -> u64 {
+    let order_id = new_order(s);
+    s.client.fulfill_order(&s.fulfiller, &order_id, &None);
+    s.client.open_dispute(&order_id, &reason(s));
+    order_id
+}
+
+#[test]
+fn test_arbiter_role_management() {
+    let s = setup();
+    let arbiter = Address::generate(&s.env);
+    assert!(!s.client.is_arbiter(&arbiter));
+
+    s.client.add_arbiter(&arbiter);
+    assert_eq!(s.env.auths()[0].0, s.admin);
+    assert!(s.client.is_arbiter(&arbiter));
+
+    s.client.remove_arbiter(&arbiter);
+    assert!(!s.client.is_arbiter(&arbiter));
+
+    s.env.set_auths(&[]);
+    assert!(s.client.try_add_arbiter(&arbiter).is_err());
+}
+
+#[test]
+fn test_open_dispute() {
+    let s = setup();
+    s.env.ledger().with_mut(|li| li.timestamp = 1_000);
+    let order_id = new_order(&s);
+    s.client.fulfill_order(&s.fulfiller, &order_id, &None);
+
+    s.env.ledger().with_mut(|li| li.timestamp = 2_000);
+    s.client.open_dispute(&order_id, &reason(&s));
+    assert_eq!(s.env.auths()[0].0, s.buyer);
+    assert_eq!(s.client.get_order(&order_id).status, OrderStatus::Disputed);
+    assert_eq!(
+        s.client.get_dispute(&order_id),
+        Dispute {
+            reason_hash: reason(&s),
+            opened_at: 2_000,
+            ruling: Ruling::Pending,
+            arbiter: None,
+            resolved_at: None,
+        }
+    );
+    assert_eq!(balance(&s, &s.client.address), PRICE);
+
+    assert_eq!(
+        s.client.try_complete_order(&order_id),
+        Err(Ok(OrderError::InvalidState))
+    );
+    assert_eq!(
+        s.client.try_open_dispute(&order_id, &reason(&s)),
+        Err(Ok(OrderError::InvalidState))
+    );
+    assert_eq!(
+        s.client.try_get_dispute(&new_order(&s)),
+        Err(Ok(OrderError::NotFound))
+    );
+}
+
+#[test]
+fn test_open_dispute_within_window_only() {
+    let s = setup();
+    let window = default_config().dispute_window;
+    let on_time = new_order(&s);
+    let late = new_order(&s);
+    s.client.fulfill_order(&s.fulfiller, &on_time, &None);
+    s.client.fulfill_order(&s.fulfiller, &late, &None);
+
+    s.env.ledger().with_mut(|li| li.timestamp += window);
+    s.client.open_dispute(&on_time, &reason(&s));
+
+    s.env.ledger().with_mut(|li| li.timestamp += 1);
+    assert_eq!(
+        s.client.try_open_dispute(&late, &reason(&s)),
+        Err(Ok(OrderError::DisputeWindowClosed))
+    );
+    assert_eq!(s.client.get_order(&late).status, OrderStatus::Delivered);
+}
+
+#[test]
+fn test_complete_order_after_dispute_window() {
+    let s = setup();
+    let order_id = new_order(&s);
+    s.client.fulfill_order(&s.fulfiller, &order_id, &None);
+
+    s.env.set_auths(&[]);
+    assert!(s.client.try_complete_order(&order_id).is_err());
+
+    let window = default_config().dispute_window;
+    s.env.ledger().with_mut(|li| li.timestamp += window + 1);
+    s.client.complete_order(&order_id);
+    assert_eq!(s.client.get_order(&order_id).status, OrderStatus::Completed);
+    assert_eq!(balance(&s, &s.seller), PRICE);
+}
+
+#[test]
+fn test_resolve_dispute_rulings() {
+    let s = setup();
+    let released = disputed_order(&s);
+    let refunded = disputed_order(&s);
+    let split = disputed_order(&s);
+    s.env.ledger().with_mut(|li| li.timestamp = 5_000);
+
+    s.client
+        .resolve_dispute(&s.arbiter, &released, &Ruling::ReleaseToSeller);
+    assert_eq!(s.env.auths()[0].0, s.arbiter);
+    assert_eq!(s.client.get_order(&released).status, OrderStatus::Completed);
+    assert_eq!(balance(&s, &s.seller), PRICE);
+
+    s.client
+        .resolve_dispute(&s.arbiter, &refunded, &Ruling::RefundBuyer);
+    assert_eq!(s.client.get_order(&refunded).status, OrderStatus::Refunded);
+    assert_eq!(balance(&s, &s.buyer), BUYER_FUNDS - 2 * PRICE);
+
+    s.client
+        .resolve_dispute(&s.arbiter, &split, &Ruling::Split(2_500));
+    assert_eq!(s.client.get_order(&split).status, OrderStatus::Refunded);
+    assert_eq!(balance(&s, &s.seller), PRICE + 25);
+    assert_eq!(balance(&s, &s.buyer), BUYER_FUNDS - PRICE - 25);
+    assert_eq!(balance(&s, &s.client.address), 0);
+
+    let dispute = s.client.get_dispute(&split);
+    assert_eq!(dispute.ruling, Ruling::Split(2_500));
+    assert_eq!(dispute.arbiter, Some(s.arbiter.clone()));
+    assert_eq!(dispute.resolved_at, Some(5_000));
+}
+
+#[test]
+fn test_resolve_dispute_validation() {
+    let s = setup();
+    let order_id = disputed_order(&s);
+
+    assert_eq!(
+        s.client
+            .try_resolve_dispute(&Address::generate(&s.env), &order_id, &Ruling::RefundBuyer),
+        Err(Ok(OrderError::Unauthorized))
+    );
+    for ruling in [Ruling::Pending, Ruling::Split(10_001)] {
+        assert_eq!(
+            s.client.try_resolve_dispute(&s.arbiter, &order_id, &ruling),
+            Err(Ok(OrderError::InvalidRuling))
+        );
+    }
+
+    s.client
+        .resolve_dispute(&s.arbiter, &order_id, &Ruling::Split(MAX_BPS));
+    assert_eq!(s.client.get_order(&order_id).status, OrderStatus::Completed);
+    assert_eq!(
+        s.client
+            .try_resolve_dispute(&s.arbiter, &order_id, &Ruling::RefundBuyer),
+        Err(Ok(OrderError::InvalidState))
+    );
+    assert_eq!(balance(&s, &s.seller), PRICE);
+}
+
+#[test]
+fn test_split_share_rounds_down_without_overflow() {
+    assert_eq!(bps_share(101, 5_000), 50);
+    assert_eq!(bps_share(9_999, 1), 0);
+    assert_eq!(bps_share(i128::MAX, MAX_BPS), i128::MAX);
+    assert_eq!(bps_share(i128::MAX, 0), 0);
+}
+
+#[test]
+fn test_dispute_events() {
+    let s = setup();
+    let order_id = new_order(&s);
+    s.client.fulfill_order(&s.fulfiller, &order_id, &None);
+
+    s.client.open_dispute(&order_id, &reason(&s));
+    assert_eq!(
+        contract_events(&s),
+        vec![
+            &s.env,
+            (
+                s.client.address.clone(),
+                (
+                    symbol_short!("order"),
+                    symbol_short!("disputed"),
+                    order_id,
+                    s.buyer.clone()
+                )
+                    .into_val(&s.env),
+                OrderEvent {
+                    version: EVENT_VERSION,
+                    actor: s.buyer.clone(),
+                    timestamp: 0,
+                }
+                .into_val(&s.env),
+            ),
+        ]
+    );
+
+    s.client
+        .resolve_dispute(&s.arbiter, &order_id, &Ruling::Split(4_000));
+    assert_eq!(
+        contract_events(&s),
+        vec![
+            &s.env,
+            (
+                s.client.address.clone(),
+                (
+                    symbol_short!("order"),
+                    symbol_short!("resolved"),
+                    order_id,
+                    s.buyer.clone()
+                )
+                    .into_val(&s.env),
+                DisputeEvent {
+                    version: EVENT_VERSION,
+                    arbiter: s.arbiter.clone(),
+                    ruling: Ruling::Split(4_000),
+                    seller_amount: 40,
+                    buyer_amount: 60,
+                    timestamp: 0,
+                }
+                .into_val(&s.env),
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_courier_role_management() {
+    let s = setup();
+    let courier = Address::generate(&s.env);
+    assert!(!s.client.is_courier(&courier));
+
+    s.client.add_courier(&courier);
+    assert_eq!(s.env.auths()[0].0, s.admin);
+    assert!(s.client.is_courier(&courier));
+
+    s.client.remove_courier(&courier);
+    assert!(!s.client.is_courier(&courier));
+
+    s.env.set_auths(&[]);
+    assert!(s.client.try_add_courier(&courier).is_err());
+}
+
+fn location(s: &Setup, seed: u8) -> BytesN<32> {
+    BytesN::from_array(&s.env, &[seed; 32])
+}
+
+#[test]
+fn test_shipment_timeline() {
+    let s = setup();
+    let courier = Address::generate(&s.env);
+    s.client.add_courier(&courier);
+    let order_id = new_order(&s);
+
+    s.env.ledger().with_mut(|li| li.timestamp = 100);
+    s.client
+        .add_checkpoint(&s.fulfiller, &order_id, &1, &location(&s, 1));
+    assert_eq!(s.env.auths()[0].0, s.fulfiller);
+    for (time, status_code) in [(200, 2), (300, 3)] {
+        s.env.ledger().with_mut(|li| li.timestamp = time);
+        s.client
+            .add_checkpoint(&courier, &order_id, &status_code, &location(&s, 2));
+    }
+
+    let (page, cursor) = s.client.get_timeline(&order_id, &None, &2);
+    assert_eq!(
+        page,
+        vec![
+            &s.env,
+            Checkpoint {
+                status_code: 1,
+                timestamp: 100,
+                actor: s.fulfiller.clone(),
+                location_hash: location(&s, 1),
+            },
+            Checkpoint {
+                status_code: 2,
+                timestamp: 200,
+                actor: courier.clone(),
+                location_hash: location(&s, 2),
+            },
+        ]
+    );
+    assert_eq!(cursor, Some(2));
+
+    let (page, cursor) = s.client.get_timeline(&order_id, &cursor, &2);
+    assert_eq!(page.len(), 1);
+    assert_eq!(page.get(0).unwrap().timestamp, 300);
+    assert_eq!(cursor, None);
+
+    let (page, cursor) = s.client.get_timeline(&new_order(&s), &None, &5);
+    assert!(page.is_empty());
+    assert_eq!(cursor, None);
+    assert_eq!(
+        s.client.try_get_timeline(&order_id, &None, &0),
+        Err(Ok(OrderError::InvalidLimit))
+    );
+    assert_eq!(
+        s.client.try_get_timeline(&99, &None, &5),
+        Err(Ok(OrderError::NotFound))
+    );
+}
+
+#[test]
+fn test_add_checkpoint_validation() {
+    let s = setup();
+    let order_id = new_order(&s);
+
+    assert_eq!(
+        s.client
+            .try_add_checkpoint(&Address::generate(&s.env), &order_id, &1, &location(&s, 1)),
+        Err(Ok(OrderError::Unauthorized))
+    );
+
+    for _ in 0..MAX_TIMELINE_LEN {
+        s.client
+            .add_checkpoint(&s.fulfiller, &order_id, &1, &location(&s, 1));
+    }
+    assert_eq!(
+        s.client
+            .try_add_checkpoint(&s.fulfiller, &order_id, &1, &location(&s, 1)),
+        Err(Ok(OrderError::TimelineFull))
+    );
+
+    let cancelled = new_order(&s);
+    s.client.cancel_order(&cancelled);
+    assert_eq!(
+        s.client
+            .try_add_checkpoint(&s.fulfiller, &cancelled, &1, &location(&s, 1)),
+        Err(Ok(OrderError::InvalidState))
+    );
+}
+
+fn review_hash(s: &Setup) -> BytesN<32> {
+    BytesN::from_array(&s.env, &[3; 32])
+}
+
+#[test]
+fn test_rate_order() {
+    let s = setup();
+    assert_eq!(
+        s.client.get_seller_rating(&s.seller),
+        SellerRating { sum: 0, count: 0 }
+    );
+    let delivered = new_order(&s);
+    let completed = new_order(&s);
+    s.client.fulfill_order(&s.fulfiller, &delivered, &None);
+    s.client.fulfill_order(&s.fulfiller, &completed, &None);
+    s.client.complete_order(&completed);
+
+    s.env.ledger().with_mut(|li| li.timestamp = 1_000);
+    s.client.rate_order(&delivered, &5, &review_hash(&s));
+    assert_eq!(s.env.auths()[0].0, s.buyer);
+    s.client.rate_order(&completed, &2, &review_hash(&s));
+
+    assert_eq!(
+        s.client.get_review(&delivered),
+        Review {
+            rating: 5,
+            content_hash: review_hash(&s),
+            timestamp: 1_000,
+        }
+    );
+    assert_eq!(
+        s.client.get_seller_rating(&s.seller),
+        SellerRating { sum: 7, count: 2 }
+    );
+}
+
+#[test]
+fn test_rate_order_validation() {
+    let s = setup();
+    let order_id = new_order(&s);
+    assert_eq!(
+        s.client.try_rate_order(&order_id, &5, &review_hash(&s)),
+        Err(Ok(OrderError::InvalidState))
+    );
+    assert_eq!(
+        s.client.try_get_review(&order_id),
+        Err(Ok(OrderError::NotFound))
+    );
+
+    s.client.fulfill_order(&s.fulfiller, &order_id, &None);
+    for rating in [MIN_RATING - 1, MAX_RATING + 1] {
+        assert_eq!(
+            s.client
+                .try_rate_order(&order_id, &rating, &review_hash(&s)),
+            Err(Ok(OrderError::InvalidRating))
+        );
+    }
+
+    s.client.rate_order(&order_id, &4, &review_hash(&s));
+    assert_eq!(
+        s.client.try_rate_order(&order_id, &1, &review_hash(&s)),
+        Err(Ok(OrderError::AlreadyRated))
+    );
+    assert_eq!(
+        s.client.get_seller_rating(&s.seller),
+        SellerRating { sum: 4, count: 1 }
+    );
+
+    let other = new_order(&s);
+    s.client.fulfill_order(&s.fulfiller, &other, &None);
+    s.env.set_auths(&[]);
+    assert!(s
+        .client
+        .try_rate_order(&other, &1, &review_hash(&s))
+        .is_err());
+}
+
+#[test]
+fn test_seller_reputation() {
+    let s = setup();
+    assert_eq!(s.client.reputation(&s.seller).fulfilled, 0);
+
+    s.env.ledger().with_mut(|li| li.timestamp = 100);
+    let quick = new_order(&s);
+    let slow = new_order(&s);
+    let rejected = new_order(&s);
+    let withdrawn = new_order(&s);
+    let expired = new_order_with_deadline(&s, 1_000);
+
+    s.env.ledger().with_mut(|li| li.timestamp = 400);
+    s.client.fulfill_order(&s.fulfiller, &quick, &None);
+    s.env.ledger().with_mut(|li| li.timestamp = 600);
+    s.client.fulfill_order(&s.fulfiller, &slow, &None);
+    s.client.reject_order(&rejected, &1);
+    // Buyer cancellations don't count against the seller
+    s.client.cancel_order(&withdrawn);
+
+    s.client.open_dispute(&quick, &reason(&s));
+    s.client.open_dispute(&slow, &reason(&s));
+    s.client
+        .resolve_dispute(&s.arbiter, &quick, &Ruling::ReleaseToSeller);
+    s.client
+        .resolve_dispute(&s.arbiter, &slow, &Ruling::Split(9_000));
+
+    s.env.ledger().with_mut(|li| li.timestamp = 1_001);
+    s.client.claim_refund(&expired);
+
+    assert_eq!(
+        s.client.reputation(&s.seller),
+        SellerReputation {
+            fulfilled: 2,
+            cancelled: 2,
+            disputed: 2,
+            disputes_lost: 1,
+            total_fulfill_time: 800,
+            avg_fulfill_time: 400,
+        }
+    );
+}
+
+#[test]
+fn test_transitions_require_auth() {
+    let s = setup();
+    let order_id = new_order(&s);
+    let stranger = Address::generate(&s.env);
+
+    assert_eq!(
+        s.client.try_fulfill_order(&stranger, &order_id, &None),
+        Err(Ok(OrderError::Unauthorized))
+    );
+
+    s.env.set_auths(&[]);
+    assert!(s.client.try_accept_order(&order_id).is_err());
+    assert!(s.client.try_cancel_order(&order_id).is_err());
+    assert_eq!(s.client.get_order(&order_id).status, OrderStatus::Created);
+}
+
+#[test]
+fn test_transition_events() {
+    let s = setup();
+    let order_id = new_order(&s);
+
+    s.client.accept_order(&order_id);
+    assert_eq!(
+        contract_events(&s),
+        vec![
+            &s.env,
+            (
+                s.client.address.clone(),
+                (
+                    symbol_short!("order"),
+                    symbol_short!("accepted"),
+                    order_id,
+                    s.buyer.clone()
+                )
+                    .into_val(&s.env),
+                OrderEvent {
+                    version: EVENT_VERSION,
+                    actor: s.seller.clone(),
+                    timestamp: 0,
+                }
+                .into_val(&s.env),
+            ),
+        ]
+    );
+
+    let order_id = new_order(&s);
+    s.client.cancel_order(&order_id);
+    assert_eq!(
+        contract_events(&s),
+        vec![
+            &s.env,
+            (
+                s.client.address.clone(),
+                (
+                    symbol_short!("order"),
+                    symbol_short!("cancelled"),
+                    order_id,
+                    s.buyer.clone()
+                )
+                    .into_val(&s.env),
+                OrderEvent {
+                    version: EVENT_VERSION,
+                    actor: s.buyer.clone(),
+                    timestamp: 0,
+                }
+                .into_val(&s.env),
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_order_events() {
+    let s = setup();
+    s.env.ledger().with_mut(|li| li.timestamp = 1_000);
+
+    let order_id = new_order(&s);
+    assert_eq!(
+        contract_events(&s),
+        vec![
+            &s.env,
+            (
+                s.client.address.clone(),
+                (
+                    symbol_short!("order"),
+                    symbol_short!("created"),
+                    order_id,
+                    s.buyer.clone()
+                )
+                    .into_val(&s.env),
+                OrderEvent {
+                    version: EVENT_VERSION,
+                    actor: s.buyer.clone(),
+                    timestamp: 1_000,
+                }
+                .into_val(&s.env),
+            ),
+        ]
+    );
+
+    s.env.ledger().with_mut(|li| li.timestamp = 2_000);
+    s.client.fulfill_order(&s.fulfiller, &order_id, &None);
+    assert_eq!(
+        contract_events(&s),
+        vec![
+            &s.env,
+            (
+                s.client.address.clone(),
+                (
+                    symbol_short!("order"),
+                    symbol_short!("fulfilled"),
+                    order_id,
+                    s.buyer.clone()
+                )
+                    .into_val(&s.env),
+                OrderEvent {
+                    version: EVENT_VERSION,
+                    actor: s.fulfiller.clone(),
+                    timestamp: 2_000,
+                }
+                .into_val(&s.env),
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_failed_fulfillment_emits_no_event() {
+    let s = setup();
+    let order_id = new_order(&s);
+    s.client.fulfill_order(&s.fulfiller, &order_id, &None);
+
+    assert!(s
+        .client
+        .try_fulfill_order(&s.fulfiller, &order_id, &None)
+        .is_err());
+    assert_eq!(contract_events(&s).len(), 0);
+}
+
+#[test]
+fn test_create_order_requires_buyer_auth() {
+    let s = setup();
+    let product = String::from_str(&s.env, "Laptop");
+
+    s.client
+        .create_order(&s.buyer, &s.seller, &product, &s.token, &PRICE);
+    assert_eq!(
+        s.env.auths(),
+        [(
+            s.buyer.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    s.client.address.clone(),
+                    Symbol::new(&s.env, "create_order"),
+                    (
+                        s.buyer.clone(),
+                        s.seller.clone(),
+                        product,
+                        s.token.clone(),
+                        PRICE,
+                    )
+                        .into_val(&s.env),
+                )),
+                sub_invocations: [AuthorizedInvocation {
+                    function: AuthorizedFunction::Contract((
+                        s.token.clone(),
+                        symbol_short!("transfer"),
+                        (s.buyer.clone(), s.client.address.clone(), PRICE).into_val(&s.env),
+                    )),
+                    sub_invocations: [].into(),
+                }]
+                .into(),
+            }
+        )]
+    );
+}
+
+#[test]
+fn test_create_order_without_auth_fails() {
+    let s = setup();
+    s.env.set_auths(&[]);
+
+    let res = s.client.try_create_order(
+        &s.buyer,
+        &s.seller,
+        &String::from_str(&s.env, "Laptop"),
+        &s.token,
+        &PRICE,
+    );
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_fulfill_order_requires_fulfiller_auth() {
+    let s = setup();
+
+    let order_id = new_order(&s);
+    s.client.fulfill_order(&s.fulfiller, &order_id, &None);
+    assert_eq!(s.env.auths()[0].0, s.fulfiller);
+}
+
+#[test]
+fn test_fulfill_order_rejects_non_fulfiller() {
+    let s = setup();
+    let stranger = Address::generate(&s.env);
+
+    let order_id = new_order(&s);
+    assert_eq!(
+        s.client.try_fulfill_order(&stranger, &order_id, &None),
+        Err(Ok(OrderError::Unauthorized))
+    );
+}
+
+#[test]
+fn test_fulfiller_role_management() {
+    let s = setup();
+    let fulfiller = Address::generate(&s.env);
+    assert!(!s.client.is_fulfiller(&fulfiller));
+
+    s.client.add_fulfiller(&fulfiller);
+    assert_eq!(s.env.auths()[0].0, s.admin);
+    assert!(s.client.is_fulfiller(&fulfiller));
+
+    s.client.remove_fulfiller(&fulfiller);
+    assert_eq!(s.env.auths()[0].0, s.admin);
+    assert!(!s.client.is_fulfiller(&fulfiller));
+
+    let order_id = new_order(&s);
+    assert_eq!(
+        s.client.try_fulfill_order(&fulfiller, &order_id, &None),
+        Err(Ok(OrderError::Unauthorized))
+    );
+}
+
+#[test]
+fn test_add_fulfiller_requires_admin() {
+    let s = setup();
+    s.env.set_auths(&[]);
+
+    let res = s.client.try_add_fulfiller(&Address::generate(&s.env));
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_config_management() {
+    let s = setup();
+    assert_eq!(s.client.get_config(), default_config());
+
+    let config = Config {
+        fulfillment_deadline: 60,
+        max_product_len: 8,
+        max_items: 2,
+        max_quantity: 5,
+        dispute_window: 60,
+        fee_bps: 250,
+    };
+    s.client.update_config(&config);
+    assert_eq!(s.env.auths()[0].0, s.admin);
+    assert_eq!(s.client.get_config(), config);
+}
+
+#[test]
+fn test_version() {
+    let s = setup();
+
+    assert_eq!(
+        s.client.version(),
+        ContractVersion {
+            schema: SCHEMA_VERSION,
+            code: CODE_VERSION,
+        }
+    );
+}
+
+#[test]
+fn test_upgrade_from_previous_code() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = Address::generate(&env);
+    let fulfiller = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let seller = Address::generate(&env);
+    let token = env
+        .register_stellar_asset_contract_v2(Address::generate(&env))
+        .address();
+    StellarAssetClient::new(&env, &token).mint(&buyer, &BUYER_FUNDS);
+
+    let prev_config = contract_prev::Config {
+        fulfillment_deadline: 7 * 24 * 60 * 60,
+        max_product_len: 64,
+        max_items: 10,
+        max_quantity: 1_000,
+        dispute_window: 3 * 24 * 60 * 60,
+        paused: false,
+    };
+    let contract_id = env.register(contract_prev::WASM, (&admin, prev_config.clone()));
+    let prev = contract_prev::Client::new(&env, &contract_id);
+    assert_eq!(prev.version().code, 1);
+    prev.add_fulfiller(&fulfiller);
+    prev.register_seller(&seller, &String::from_str(&env, "Acme Supplies"));
+    prev.approve_seller(&seller);
+    let product = String::from_str(&env, "Laptop");
+    let delivered = prev.create_order(&buyer, &seller, &product, &token, &PRICE, &None, &None);
+    prev.fulfill_order(&fulfiller, &delivered, &None);
+    let open = prev.create_order(&buyer, &seller, &product, &token, &PRICE, &None, &None);
+    prev.update_config(&contract_prev::Config {
+        paused: true,
+        ..prev_config
+    });
+
+    let new_wasm_hash = env.deployer().upload_contract_wasm(contract_next::WASM);
+    prev.upgrade(&new_wasm_hash);
+    assert_eq!(env.auths()[0].0, admin);
+
+    let client = OrderFulfillmentVerifierClient::new(&env, &contract_id);
+    assert_eq!(
+        client.version(),
+        ContractVersion {
+            schema: SCHEMA_VERSION,
+            code: CODE_VERSION,
+        }
+    );
+    assert_eq!(client.get_config(), default_config());
+    // The paused flag of the old config still pauses the whole order flow
+    assert_eq!(
+        client.paused(),
+        PauseState {
+            creation: true,
+            fulfillment: true,
+        }
+    );
+    assert_eq!(
+        client.try_complete_order(&delivered),
+        Err(Ok(OrderError::Paused))
+    );
+    client.unpause(&PauseScope::All);
+
+    // Orders written by the previous code are read back and keep working
+    assert_eq!(client.get_order(&delivered).status, OrderStatus::Delivered);
+    assert_eq!(client.get_order(&open).status, OrderStatus::Created);
+    client.complete_order(&delivered);
+    client.fulfill_order(&fulfiller, &open, &None);
+    client.complete_order(&open);
+    assert_eq!(TokenClient::new(&env, &token).balance(&seller), 2 * PRICE);
+    assert!(client.is_fulfiller(&fulfiller));
+    assert_eq!(client.list_orders_by_buyer(&buyer, &None, &10).0.len(), 2);
+}
+
+#[test]
+fn test_upgrade_requires_admin() {
+    let s = setup();
+    let new_wasm_hash = s.env.deployer().upload_contract_wasm(contract_next::WASM);
+    s.env.set_auths(&[]);
+
+    assert!(s.client.try_upgrade(&new_wasm_hash).is_err());
+    assert_eq!(s.client.version().code, CODE_VERSION);
+}
+
+fn set_fee(s: &Setup, fee_bps: u32) {
+    s.client.update_config(&Config {
+        fee_bps,
+        ..default_config()
+    });
+}
+
+#[test]
+fn test_fee_taken_on_completion() {
+    let s = setup();
+    set_fee(&s, 250);
+    let order_id = new_order(&s);
+    s.client.fulfill_order(&s.fulfiller, &order_id, &None);
+    assert_eq!(s.client.fee_balance(&s.token), 0);
+
+    s.client.complete_order(&order_id);
+    assert_eq!(balance(&s, &s.seller), PRICE - 2);
+    assert_eq!(s.client.fee_balance(&s.token), 2);
+    assert_eq!(balance(&s, &s.client.address), 2);
+}
+
+#[test]
+fn test_fee_taken_on_partial_releases_and_rulings() {
+    let s = setup();
+    set_fee(&s, 1_000);
+    let order_id = new_multi_item_order(&s);
+
+    // 2 * 25 released, 10% of it kept
+    s.client
+        .fulfill_items(&s.fulfiller, &order_id, &vec![&s.env, (0, 2)], &None);
+    assert_eq!(balance(&s, &s.seller), 45);
+    assert_eq!(s.client.fee_balance(&s.token), 5);
+
+    // Of the remaining 85, the seller is awarded 50% and pays the fee on that share only
+    s.client.fulfill_order(&s.fulfiller, &order_id, &None);
+    s.client.open_dispute(&order_id, &reason(&s));
+    s.client
+        .resolve_dispute(&s.arbiter, &order_id, &Ruling::Split(5_000));
+    assert_eq!(balance(&s, &s.seller), 45 + 42 - 4);
+    assert_eq!(s.client.fee_balance(&s.token), 5 + 4);
+    assert_eq!(balance(&s, &s.buyer), BUYER_FUNDS - 135 + 43);
+
+    // Refunds to the buyer are fee-free
+    let cancelled = new_order(&s);
+    s.client.cancel_order(&cancelled);
+    assert_eq!(balance(&s, &s.buyer), BUYER_FUNDS - 135 + 43);
+    assert_eq!(balance(&s, &s.client.address), 9);
+}
+
+#[test]
+fn test_fee_rounding_edges() {
+    // Rounded down, in the seller's favour
+    assert_eq!(platform_fee(100, 250), 2);
+    assert_eq!(platform_fee(39, 250), 0);
+    assert_eq!(platform_fee(40, 250), 1);
+    assert_eq!(platform_fee(1, MAX_BPS - 1), 0);
+    assert_eq!(platform_fee(MAX_BPS as i128, 1), 1);
+    assert_eq!(platform_fee(i128::MAX, 0), 0);
+    assert_eq!(platform_fee(i128::MAX, MAX_BPS), i128::MAX);
+    assert_eq!(platform_fee(i128::MAX, 1), i128::MAX / MAX_BPS as i128);
+}
+
+#[test]
+fn test_fee_at_max_and_zero_rate() {
+    let s = setup();
+    set_fee(&s, MAX_FEE_BPS);
+    let order_id = new_order(&s);
+    s.client.fulfill_order(&s.fulfiller, &order_id, &None);
+    s.client.complete_order(&order_id);
+    assert_eq!(balance(&s, &s.seller), PRICE - 10);
+    assert_eq!(s.client.fee_balance(&s.token), 10);
+
+    set_fee(&s, 0);
+    let order_id = new_order(&s);
+    s.client.fulfill_order(&s.fulfiller, &order_id, &None);
+    s.client.complete_order(&order_id);
+    assert_eq!(balance(&s, &s.seller), 2 * PRICE - 10);
+    assert_eq!(s.client.fee_balance(&s.token), 10);
+}
+
+#[test]
+fn test_fee_fixed_at_order_creation() {
+    let s = setup();
+    set_fee(&s, 250);
+    let order_id = new_order(&s);
+    assert_eq!(s.client.get_order(&order_id).fee_bps, 250);
+
+    // Raising the fee later doesn't touch orders already placed
+    set_fee(&s, MAX_FEE_BPS);
+    s.client.fulfill_order(&s.fulfiller, &order_id, &None);
+    s.client.complete_order(&order_id);
+    assert_eq!(balance(&s, &s.seller), PRICE - 2);
+    assert_eq!(s.client.fee_balance(&s.token), 2);
+}
+
+#[test]
+fn test_invalid_fee_rejected() {
+    let s = setup();
+
+    assert_eq!(
+        s.client.try_update_config(&Config {
+            fee_bps: MAX_FEE_BPS + 1,
+            ..default_config()
+        }),
+        Err(Ok(OrderError::InvalidFee))
+    );
+    assert_eq!(s.client.get_config(), default_config());
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #30)")]
+fn test_constructor_rejects_invalid_fee() {
+    let env = Env::default();
+    let config = Config {
+        fee_bps: MAX_FEE_BPS + 1,
+        ..default_config()
+    };
+    env.register(OrderFulfillmentVerifier, (Address::generate(&env), config));
+}
+
+#[test]
+fn test_withdraw_fees() {
+    let s = setup();
+    set_fee(&s, MAX_FEE_BPS);
+    let order_id = new_order(&s);
+    s.client.fulfill_order(&s.fulfiller, &order_id, &None);
+    s.client.complete_order(&order_id);
+    let treasury = Address::generate(&s.env);
+
+    s.client.withdraw_fees(&s.token, &treasury, &3);
+    assert_eq!(s.env.auths()[0].0, s.admin);
+    assert_eq!(balance(&s, &treasury), 3);
+    assert_eq!(s.client.fee_balance(&s.token), 7);
+
+    assert_eq!(
+        s.client.try_withdraw_fees(&s.token, &treasury, &8),
+        Err(Ok(OrderError::InsufficientFees))
+    );
+    assert_eq!(
+        s.client.try_withdraw_fees(&s.token, &treasury, &0),
+        Err(Ok(OrderError::InvalidAmount))
+    );
+    // Balances are kept per token
+    let other_token = Address::generate(&s.env);
+    assert_eq!(
+        s.client.try_withdraw_fees(&other_token, &treasury, &1),
+        Err(Ok(OrderError::InsufficientFees))
+    );
+
+    s.client.withdraw_fees(&s.token, &treasury, &7);
+    assert_eq!(s.client.fee_balance(&s.token), 0);
+    assert_eq!(balance(&s, &s.client.address), 0);
+
+    s.env.set_auths(&[]);
+    assert!(s.client.try_withdraw_fees(&s.token, &treasury, &1).is_err());
+}
 
 #[test]
-fn test() {
-    let env = Env::default();
-    let contract_id = env.register(Contract, ());
-    let client = ContractClient::new(&env, &contract_id);
+fn test_update_config_requires_admin() {
+    let s = setup();
+    s.env.set_auths(&[]);
+
+    assert!(s.client.try_update_config(&default_config()).is_err());
+}
+
+#[test]
+fn test_create_order_rejects_long_product() {
+    let s = setup();
+    s.client.update_config(&Config {
+        max_product_len: 4,
+        ..default_config()
+    });
+
+    assert_eq!(
+        s.client.try_create_order(
+            &s.buyer,
+            &s.seller,
+            &String::from_str(&s.env, "Laptop"),
+            &s.token,
+            &PRICE,
+        ),
+        Err(Ok(OrderError::ProductTooLong))
+    );
+}
+
+#[test]
+fn test_create_order_when_paused() {
+    let s = setup();
+    s.client.pause(&s.admin, &PauseScope::Creation);
+
+    assert_eq!(
+        s.client.try_create_order(
+            &s.buyer,
+            &s.seller,
+            &String::from_str(&s.env, "Laptop"),
+            &s.token,
+            &PRICE,
+        ),
+        Err(Ok(OrderError::Paused))
+    );
+}
+
+#[test]
+fn test_fulfill_order_when_paused() {
+    let s = setup();
+    let order_id = new_order(&s);
+    s.client.pause(&s.admin, &PauseScope::Fulfillment);
+
+    assert_eq!(
+        s.client.try_fulfill_order(&s.fulfiller, &order_id, &None),
+        Err(Ok(OrderError::Paused))
+    );
+}
+
+#[test]
+fn test_guardian_role_management() {
+    let s = setup();
+    let guardian = Address::generate(&s.env);
+    assert!(!s.client.is_guardian(&guardian));
+
+    s.client.add_guardian(&guardian);
+    assert_eq!(s.env.auths()[0].0, s.admin);
+    assert!(s.client.is_guardian(&guardian));
+
+    s.client.remove_guardian(&guardian);
+    assert!(!s.client.is_guardian(&guardian));
+    assert_eq!(
+        s.client.try_pause(&guardian, &PauseScope::All),
+        Err(Ok(OrderError::Unauthorized))
+    );
+
+    s.env.set_auths(&[]);
+    assert!(s.client.try_add_guardian(&guardian).is_err());
+}
+
+#[test]
+fn test_pause_scopes() {
+    let s = setup();
+    let guardian = Address::generate(&s.env);
+    s.client.add_guardian(&guardian);
+    let order_id = new_order(&s);
+
+    s.client.pause(&guardian, &PauseScope::Creation);
+    assert_eq!(s.env.auths()[0].0, guardian);
+    assert_eq!(
+        s.client.paused(),
+        PauseState {
+            creation: true,
+            fulfillment: false,
+        }
+    );
+    assert_eq!(
+        s.client
+            .try_create_orders(&s.buyer, &s.seller, &batch_products(&s, &[PRICE]), &s.token),
+        Err(Ok(OrderError::Paused))
+    );
+    // Existing orders keep moving while only creation is paused
+    s.client.accept_order(&order_id);
+
+    s.client.pause(&s.admin, &PauseScope::All);
+    assert_eq!(
+        s.client.try_ship_order(&s.fulfiller, &order_id),
+        Err(Ok(OrderError::Paused))
+    );
+    // Views keep working
+    assert_eq!(s.client.get_order(&order_id).status, OrderStatus::Accepted);
+
+    s.client.unpause(&PauseScope::Creation);
+    assert_eq!(s.env.auths()[0].0, s.admin);
+    assert_eq!(
+        s.client.paused(),
+        PauseState {
+            creation: false,
+            fulfillment: true,
+        }
+    );
+    new_order(&s);
+
+    s.client.unpause(&PauseScope::Fulfillment);
+    s.client.ship_order(&s.fulfiller, &order_id);
+}
+
+#[test]
+fn test_creation_pause_blocks_seller_registration() {
+    let s = setup();
+    let seller = Address::generate(&s.env);
+    let metadata = String::from_str(&s.env, "Widgets Co");
+
+    s.client.pause(&s.admin, &PauseScope::Fulfillment);
+    s.client.register_seller(&seller, &metadata);
+
+    s.client.pause(&s.admin, &PauseScope::Creation);
+    let newcomer = Address::generate(&s.env);
+    for address in [&newcomer, &seller] {
+        assert_eq!(
+            s.client.try_register_seller(address, &metadata),
+            Err(Ok(OrderError::Paused))
+        );
+    }
+    assert_eq!(
+        s.client.try_get_seller(&newcomer),
+        Err(Ok(OrderError::SellerNotFound))
+    );
+
+    s.client.unpause(&PauseScope::Creation);
+    s.client.register_seller(&newcomer, &metadata);
+}
+
+#[test]
+fn test_fulfillment_pause_stops_order_timers() {
+    let s = setup();
+    let config = default_config();
+    s.env.ledger().with_mut(|li| li.timestamp = 1_000);
+    let disputed = new_order(&s);
+    let completed = new_order(&s);
+    let lapsed = new_order(&s);
+    for order_id in [disputed, completed, lapsed] {
+        s.client.fulfill_order(&s.fulfiller, &order_id, &None);
+    }
+    let fulfilled = new_order(&s);
+    let refunded = new_order(&s);
+
+    // A pause outlasting both the dispute window and the fulfillment deadline
+    let pause = config.fulfillment_deadline + 1;
+    s.client.pause(&s.admin, &PauseScope::Fulfillment);
+    s.env.ledger().with_mut(|li| li.timestamp += pause);
+    s.client.unpause(&PauseScope::Fulfillment);
+
+    // Buyers keep the dispute window and sellers the time to deliver they had
+    s.client.open_dispute(&disputed, &reason(&s));
+    s.client.complete_order(&completed);
+    assert_eq!(s.env.auths()[0].0, s.buyer);
+    s.client.fulfill_order(&s.fulfiller, &fulfilled, &None);
+    assert_eq!(
+        s.client.try_claim_refund(&refunded),
+        Err(Ok(OrderError::DeadlineNotReached))
+    );
+    assert_eq!(
+        s.client.get_order(&fulfilled).deadline,
+        1_000 + config.fulfillment_deadline + pause
+    );
+    assert_eq!(s.client.reputation(&s.seller).cancelled, 0);
+
+    // Past the extended deadline the order can be refunded, and past the extended
+    // dispute window anyone may complete a delivered order
+    s.env
+        .ledger()
+        .with_mut(|li| li.timestamp = 1_000 + config.fulfillment_deadline + pause + 1);
+    s.env.set_auths(&[]);
+    s.client.complete_order(&lapsed);
+    s.env.mock_all_auths();
+    assert_eq!(
+        s.client.try_fulfill_order(&s.fulfiller, &refunded, &None),
+        Err(Ok(OrderError::DeadlineExpired))
+    );
+    s.client.claim_refund(&refunded);
+}
+
+#[test]
+fn test_fulfillment_pause_blocks_every_order_change() {
+    let s = setup();
+    let created = new_order(&s);
+    let delivered = new_order(&s);
+    s.client.fulfill_order(&s.fulfiller, &delivered, &None);
+    let disputed = disputed_order(&s);
+    s.client.pause(&s.admin, &PauseScope::Fulfillment);
+    let paused = Err(Ok(OrderError::Paused));
+
+    assert_eq!(s.client.try_accept_order(&created), paused);
+    assert_eq!(s.client.try_ship_order(&s.fulfiller, &created), paused);
+    assert_eq!(
+        s.client
+            .try_fulfill_items(&s.fulfiller, &created, &vec![&s.env, (0, 1)], &None),
+        paused
+    );
+    assert_eq!(
+        s.client
+            .try_fulfill_orders(&s.fulfiller, &vec![&s.env, created]),
+        Err(Ok(OrderError::Paused))
+    );
+    let (_, public_key) = attester_key(&s, 1);
+    assert_eq!(
+        s.client.try_fulfill_with_attestation(
+            &created,
+            &attestation(&s, created, &public_key, 1),
+            &BytesN::from_array(&s.env, &[0; 64])
+        ),
+        paused
+    );
+    assert_eq!(s.client.try_cancel_order(&created), paused);
+    assert_eq!(s.client.try_reject_order(&created, &1), paused);
+    assert_eq!(s.client.try_claim_refund(&created), paused);
+    assert_eq!(
+        s.client
+            .try_add_checkpoint(&s.fulfiller, &created, &1, &location(&s, 1)),
+        paused
+    );
+    assert_eq!(s.client.try_complete_order(&delivered), paused);
+    assert_eq!(s.client.try_open_dispute(&delivered, &reason(&s)), paused);
+    assert_eq!(
+        s.client.try_rate_order(&delivered, &5, &review_hash(&s)),
+        paused
+    );
+    assert_eq!(
+        s.client
+            .try_resolve_dispute(&s.arbiter, &disputed, &Ruling::RefundBuyer),
+        paused
+    );
+}
+
+#[test]
+fn test_pause_requires_admin_or_guardian() {
+    let s = setup();
+    let guardian = Address::generate(&s.env);
+    s.client.add_guardian(&guardian);
+
+    assert_eq!(
+        s.client
+            .try_pause(&Address::generate(&s.env), &PauseScope::All),
+        Err(Ok(OrderError::Unauthorized))
+    );
+    s.client.pause(&guardian, &PauseScope::All);
+
+    // Only the admin can resume
+    s.env.set_auths(&[]);
+    assert!(s.client.try_unpause(&PauseScope::All).is_err());
+    assert_eq!(
+        s.client.paused(),
+        PauseState {
+            creation: true,
+            fulfillment: true,
+        }
+    );
+}
+
+#[test]
+fn test_fulfill_order_deadline() {
+    let s = setup();
+    let deadline = default_config().fulfillment_deadline;
+
+    let on_time = new_order(&s);
+    let late = new_order(&s);
+    s.env.ledger().with_mut(|li| li.timestamp += deadline);
+    s.client.fulfill_order(&s.fulfiller, &on_time, &None);
+
+    s.env.ledger().with_mut(|li| li.timestamp += 1);
+    assert_eq!(
+        s.client.try_fulfill_order(&s.fulfiller, &late, &None),
+        Err(Ok(OrderError::DeadlineExpired))
+    );
+    assert_eq!(s.client.get_order(&late).status, OrderStatus::Created);
+}
+
+fn new_order_with_deadline(s: &Setup, deadline: u64) -> u64 {
+    s.client.create_order_with_items(
+        &s.buyer,
+        &s.seller,
+        &vec![&s.env, item(s, "Laptop", 1, PRICE)],
+        &s.token,
+        &None,
+        &Some(deadline),
+    )
+}
+
+#[test]
+fn test_order_deadline_from_config_or_override() {
+    let s = setup();
+    s.env.ledger().with_mut(|li| li.timestamp = 1_000);
+
+    let order_id = new_order(&s);
+    assert_eq!(
+        s.client.get_order(&order_id).deadline,
+        1_000 + default_config().fulfillment_deadline
+    );
+
+    let order_id = new_order_with_deadline(&s, 5_000);
+    assert_eq!(s.client.get_order(&order_id).deadline, 5_000);
+
+    for deadline in [999, 1_000] {
+        assert_eq!(
+            s.client.try_create_order_with_items(
+                &s.buyer,
+                &s.seller,
+                &vec![&s.env, item(&s, "Laptop", 1, PRICE)],
+                &s.token,
+                &None,
+                &Some(deadline),
+            ),
+            Err(Ok(OrderError::InvalidDeadline))
+        );
+    }
+}
+
+#[test]
+fn test_override_deadline_applies_to_fulfillment() {
+    let s = setup();
+    s.env.ledger().with_mut(|li| li.timestamp = 1_000);
+    let order_id = new_order_with_deadline(&s, 2_000);
+
+    // Shortening the config afterwards does not affect existing orders
+    s.client.update_config(&Config {
+        fulfillment_deadline: 1,
+        ..default_config()
+    });
+    s.env.ledger().with_mut(|li| li.timestamp = 2_001);
+    assert_eq!(
+        s.client.try_fulfill_order(&s.fulfiller, &order_id, &None),
+        Err(Ok(OrderError::DeadlineExpired))
+    );
+
+    let order_id = new_order_with_deadline(&s, 3_000);
+    s.env.ledger().with_mut(|li| li.timestamp = 3_000);
+    s.client.fulfill_order(&s.fulfiller, &order_id, &None);
+}
+
+#[test]
+fn test_claim_refund_after_deadline() {
+    let s = setup();
+    s.env.ledger().with_mut(|li| li.timestamp = 1_000);
+    let order_id = new_order_with_deadline(&s, 2_000);
+    s.client.accept_order(&order_id);
+    s.client.ship_order(&s.fulfiller, &order_id);
+
+    s.env.ledger().with_mut(|li| li.timestamp = 2_000);
+    assert_eq!(
+        s.client.try_claim_refund(&order_id),
+        Err(Ok(OrderError::DeadlineNotReached))
+    );
+
+    s.env.ledger().with_mut(|li| li.timestamp = 2_001);
+    s.client.claim_refund(&order_id);
+    assert_eq!(s.env.auths()[0].0, s.buyer);
+
+    let order = s.client.get_order(&order_id);
+    assert_eq!(order.status, OrderStatus::Refunded);
+    assert_eq!(order.status_times.get(OrderStatus::Refunded), Some(2_001));
+    assert_eq!(balance(&s, &s.buyer), BUYER_FUNDS);
+    assert_eq!(balance(&s, &s.client.address), 0);
+
+    assert_eq!(
+        s.client.try_claim_refund(&order_id),
+        Err(Ok(OrderError::InvalidState))
+    );
+    assert_eq!(
+        s.client.try_fulfill_order(&s.fulfiller, &order_id, &None),
+        Err(Ok(OrderError::DeadlineExpired))
+    );
+}
+
+#[test]
+fn test_claim_refund_rejects_settled_orders() {
+    let s = setup();
+    let delivered = new_order(&s);
+    let cancelled = new_order(&s);
+    s.client.fulfill_order(&s.fulfiller, &delivered, &None);
+    s.client.cancel_order(&cancelled);
+
+    let deadline = s.client.get_order(&delivered).deadline;
+    s.env.ledger().with_mut(|li| li.timestamp = deadline + 1);
+    for order_id in [delivered, cancelled] {
+        assert_eq!(
+            s.client.try_claim_refund(&order_id),
+            Err(Ok(OrderError::InvalidState))
+        );
+    }
+
+    // Only disputed orders can be ruled on
+    let open = new_order(&s);
+    assert_eq!(
+        s.client
+            .try_resolve_dispute(&s.arbiter, &open, &Ruling::RefundBuyer),
+        Err(Ok(OrderError::InvalidState))
+    );
+}
+
+#[test]
+fn test_orders_are_persistent_entries() {
+    let s = setup();
+
+    let order_id = new_order(&s);
+    s.env.as_contract(&s.client.address, || {
+        assert!(s.env.storage().persistent().has(&OrderKey::Order(order_id)));
+        assert!(!s.env.storage().instance().has(&OrderKey::Order(order_id)));
+        assert_eq!(
+            s.env
+                .storage()
+                .instance()
+                .get::<_, u64>(&OrderKey::OrderCount),
+            Some(1)
+        );
+    });
+}
+
+// Rewrite an order as a bare V1 struct, the way it was stored before versioning
+fn store_as_v1(s: &Setup, order_id: u64) {
+    let order = s.client.get_order(&order_id);
+    let legacy = OrderV1 {
+        order_id: order.order_id,
+        buyer: order.buyer,
+        seller: order.seller,
+        items: order.items,
+        token: order.token,
+        amount: order.amount,
+        fulfilled: order.fulfilled,
+        released: order.released,
+        delivery_hash: order.delivery_hash,
+        attester: order.attester,
+        status: order.status,
+        status_times: order.status_times,
+        timestamp: order.timestamp,
+        deadline: order.deadline,
+    };
+    s.env.as_contract(&s.client.address, || {
+        s.env
+            .storage()
+            .persistent()
+            .set(&OrderKey::Order(order_id), &legacy);
+    });
+}
+
+fn stored_order(s: &Setup, order_id: u64) -> Option<StoredOrder> {
+    s.env.as_contract(&s.client.address, || {
+        let raw: Val = s
+            .env
+            .storage()
+            .persistent()
+            .get(&OrderKey::Order(order_id))
+            .unwrap();
+        StoredOrder::try_from_val(&s.env, &raw).ok()
+    })
+}
+
+#[test]
+fn test_orders_stored_with_schema_version() {
+    let s = setup();
+    s.env.ledger().with_mut(|li| li.timestamp = 100);
+    let order_id = new_order(&s);
+    assert_eq!(s.client.get_order(&order_id).updated_at, 100);
+
+    s.env.ledger().with_mut(|li| li.timestamp = 200);
+    s.client.accept_order(&order_id);
+    let order = s.client.get_order(&order_id);
+    assert_eq!(order.updated_at, 200);
+    assert_eq!(stored_order(&s, order_id), Some(StoredOrder::V2(order)));
+}
+
+#[test]
+fn test_v1_order_upgraded_on_access() {
+    let s = setup();
+    s.env.ledger().with_mut(|li| li.timestamp = 100);
+    let order_id = new_order(&s);
+    s.env.ledger().with_mut(|li| li.timestamp = 200);
+    s.client.accept_order(&order_id);
+    store_as_v1(&s, order_id);
+    assert_eq!(stored_order(&s, order_id), None);
+
+    s.env.ledger().with_mut(|li| li.timestamp = 300);
+    let order = s.client.get_order(&order_id);
+    assert_eq!(order.status, OrderStatus::Accepted);
+    assert_eq!(order.updated_at, 200);
+    assert_eq!(stored_order(&s, order_id), Some(StoredOrder::V2(order)));
+
+    // Write paths read through the same upgrade
+    store_as_v1(&s, order_id);
+    s.client.ship_order(&s.fulfiller, &order_id);
+    let order = s.client.get_order(&order_id);
+    assert_eq!(order.status, OrderStatus::Shipped);
+    assert_eq!(order.updated_at, 300);
+}
+
+#[test]
+fn test_v1_order_upgraded_without_fee() {
+    let s = setup();
+    set_fee(&s, 250);
+    let order_id = new_order(&s);
+    store_as_v1(&s, order_id);
+
+    // Orders placed before the fee existed never agreed to one
+    let order = s.client.get_order(&order_id);
+    assert_eq!(order.fee_bps, 0);
+    assert_eq!(stored_order(&s, order_id), Some(StoredOrder::V2(order)));
+    s.client.fulfill_order(&s.fulfiller, &order_id, &None);
+    s.client.complete_order(&order_id);
+    assert_eq!(balance(&s, &s.seller), PRICE);
+}
+
+// Rewrite the config as a bare V1 struct, the way it was stored before versioning
+fn store_config_as_v1(s: &Setup, paused: bool) {
+    let config = s.client.get_config();
+    let legacy = ConfigV1 {
+        fulfillment_deadline: config.fulfillment_deadline,
+        max_product_len: config.max_product_len,
+        max_items: config.max_items,
+        max_quantity: config.max_quantity,
+        dispute_window: config.dispute_window,
+        paused,
+    };
+    s.env.as_contract(&s.client.address, || {
+        s.env.storage().instance().remove(&OrderKey::Paused);
+        s.env.storage().instance().set(&OrderKey::Config, &legacy);
+    });
+}
+
+#[test]
+fn test_v1_config_upgraded_on_access() {
+    let s = setup();
+    let order_id = new_order(&s);
+
+    store_config_as_v1(&s, false);
+    assert_eq!(s.client.get_config(), default_config());
+    s.client.accept_order(&order_id);
+
+    // A legacy paused flag pauses the whole order flow
+    store_config_as_v1(&s, true);
+    assert_eq!(
+        s.client.paused(),
+        PauseState {
+            creation: true,
+            fulfillment: true,
+        }
+    );
+    assert_eq!(
+        s.client.try_ship_order(&s.fulfiller, &order_id),
+        Err(Ok(OrderError::Paused))
+    );
+    s.client.unpause(&PauseScope::All);
+    s.client.ship_order(&s.fulfiller, &order_id);
+
+    // Replacing a legacy config keeps its paused flag
+    store_config_as_v1(&s, true);
+    s.client.update_config(&default_config());
+    assert_eq!(
+        s.client.try_ship_order(&s.fulfiller, &order_id),
+        Err(Ok(OrderError::Paused))
+    );
+    s.env.as_contract(&s.client.address, || {
+        let raw: Val = s.env.storage().instance().get(&OrderKey::Config).unwrap();
+        assert_eq!(
+            StoredConfig::try_from_val(&s.env, &raw),
+            Ok(StoredConfig::V2(default_config()))
+        );
+    });
+}
+
+#[test]
+fn test_migrate_range() {
+    let s = setup();
+    for order_id in 1..=4 {
+        new_order(&s);
+        if order_id != 3 {
+            store_as_v1(&s, order_id);
+        }
+    }
+
+    assert_eq!(s.client.migrate_range(&1, &10), 3);
+    assert_eq!(s.env.auths()[0].0, s.admin);
+    for order_id in 1..=4 {
+        assert!(stored_order(&s, order_id).is_some());
+    }
+    assert_eq!(s.client.migrate_range(&1, &4), 0);
+
+    assert_eq!(
+        s.client.try_migrate_range(&2, &1),
+        Err(Ok(OrderError::InvalidLimit))
+    );
+    assert_eq!(
+        s.client.try_migrate_range(&1, &(MAX_PAGE_SIZE as u64 + 1)),
+        Err(Ok(OrderError::InvalidLimit))
+    );
+    s.env.set_auths(&[]);
+    assert!(s.client.try_migrate_range(&1, &4).is_err());
+}
+
+#[test]
+fn test_read_bumps_order_ttl() {
+    let s = setup();
+
+    let order_id = new_order(&s);
+    assert_eq!(order_ttl(&s, order_id), ORDER_BUMP_AMOUNT);
+
+    // Past the threshold, a read extends the entry again
+    s.env
+        .ledger()
+        .with_mut(|li| li.sequence_number += ORDER_BUMP_AMOUNT - ORDER_LIFETIME_THRESHOLD + 1);
+    assert!(order_ttl(&s, order_id) < ORDER_LIFETIME_THRESHOLD);
+    s.client.get_order(&order_id);
+    assert_eq!(order_ttl(&s, order_id), ORDER_BUMP_AMOUNT);
+    assert_eq!(
+        entry_ttl(&s, &OrderKey::QueueNode(order_id)),
+        ORDER_BUMP_AMOUNT
+    );
+}
+
+#[test]
+fn test_extend_order_ttl() {
+    let s = setup();
+
+    new_order(&s);
+    let disputed = new_order(&s);
+    s.client
+        .add_checkpoint(&s.fulfiller, &disputed, &1, &location(&s, 1));
+    s.client.fulfill_order(&s.fulfiller, &disputed, &None);
+    s.client.open_dispute(&disputed, &reason(&s));
+    let rated = new_order(&s);
+    s.client.fulfill_order(&s.fulfiller, &rated, &None);
+    s.client.complete_order(&rated);
+    s.client.rate_order(&rated, &5, &review_hash(&s));
+
+    s.client.extend_order_ttl(&disputed);
+    assert_eq!(s.env.auths()[0].0, s.admin);
+    s.client.extend_order_ttl(&rated);
+
+    // Every entry kept for the order lives as long as the order itself
+    let max_ttl = s
+        .env
+        .as_contract(&s.client.address, || s.env.storage().max_ttl());
+    for key in [
+        OrderKey::Order(disputed),
+        OrderKey::QueueNode(disputed),
+        OrderKey::BuyerOrder(s.buyer.clone(), 1),
+        OrderKey::Dispute(disputed),
+        OrderKey::Timeline(disputed),
+        OrderKey::Order(rated),
+        OrderKey::QueueNode(rated),
+        OrderKey::BuyerOrder(s.buyer.clone(), 2),
+        OrderKey::Review(rated),
+    ] {
+        assert_eq!(entry_ttl(&s, &key), max_ttl);
+    }
+    assert_eq!(s.client.get_order(&rated).buyer_slot, Some(2));
+}
+
+#[test]
+fn test_extend_order_ttl_unknown_order() {
+    let s = setup();
+
+    assert_eq!(
+        s.client.try_extend_order_ttl(&7),
+        Err(Ok(OrderError::NotFound))
+    );
+}
+
+#[test]
+fn test_create_order_cost_is_flat() {
+    let s = setup();
+    let product = String::from_str(&s.env, "Laptop");
+
+    for _ in 0..3 {
+        s.client
+            .create_order(&s.buyer, &s.seller, &product, &s.token, &PRICE);
+    }
+    let early = s.env.cost_estimate().resources();
+
+    for _ in 0..199 {
+        s.client
+            .create_order(&s.buyer, &s.seller, &product, &s.token, &PRICE);
+    }
+    s.client
+        .create_order(&s.buyer, &s.seller, &product, &s.token, &PRICE);
+    let late = s.env.cost_estimate().resources();
+
+    // Orders live in their own entries, so the instance entry does not grow
+    // and the 203rd order touches exactly as much ledger data as the 3rd
+    // (the first orders differ as the seller queue is still filling up)
+    assert_eq!(late.read_entries, early.read_entries);
+    assert_eq!(late.write_entries, early.write_entries);
+    assert_eq!(late.read_bytes, early.read_bytes);
+    assert_eq!(late.write_bytes, early.write_bytes);
+}
+
+fn batch_products(s: &Setup, amounts: &[i128]) -> Vec<(String, i128)> {
+    let mut products = Vec::new(&s.env);
+    for amount in amounts {
+        products.push_back((String::from_str(&s.env, "Laptop"), *amount));
+    }
+    products
+}
+
+#[test]
+fn test_create_orders() {
+    let s = setup();
+
+    let results = s.client.create_orders(
+        &s.buyer,
+        &s.seller,
+        &batch_products(&s, &[PRICE, 0, 2 * PRICE]),
+        &s.token,
+    );
+    assert_eq!(
+        results,
+        vec![
+            &s.env,
+            BatchResult::Ok(1),
+            BatchResult::Err(OrderError::InvalidAmount as u32),
+            BatchResult::Ok(2),
+        ]
+    );
+    assert_eq!(s.env.auths().len(), 1);
+    assert_eq!(s.env.auths()[0].0, s.buyer);
+    assert_eq!(s.client.get_order(&2).amount, 2 * PRICE);
+    assert_eq!(balance(&s, &s.client.address), 3 * PRICE);
+
+    let too_many = [PRICE; MAX_BATCH_SIZE as usize + 1];
+    assert_eq!(
+        s.client.try_create_orders(
+            &s.buyer,
+            &s.seller,
+            &batch_products(&s, &too_many),
+            &s.token
+        ),
+        Err(Ok(OrderError::BatchTooLarge))
+    );
+}
+
+#[test]
+fn test_create_orders_reports_insufficient_funds() {
+    let s = setup();
+
+    // The second order is more than what the first one left the buyer
+    let results = s.client.create_orders(
+        &s.buyer,
+        &s.seller,
+        &batch_products(&s, &[PRICE, BUYER_FUNDS, PRICE]),
+        &s.token,
+    );
+    assert_eq!(
+        results,
+        vec![
+            &s.env,
+            BatchResult::Ok(1),
+            BatchResult::Err(OrderError::InsufficientFunds as u32),
+            BatchResult::Ok(2),
+        ]
+    );
+    assert_eq!(balance(&s, &s.buyer), BUYER_FUNDS - 2 * PRICE);
+}
 
-    let words = client.hello(&String::from_str(&env, "Dev"));
+#[test]
+fn test_fulfill_orders() {
+    let s = setup();
+    let delivered = new_order(&s);
+    s.client.fulfill_order(&s.fulfiller, &delivered, &None);
+    let open = new_order(&s);
+    let with_code = new_order_with_code(&s, &Bytes::from_slice(&s.env, b"483920"));
+    let also_open = new_order(&s);
+
+    let results = s.client.fulfill_orders(
+        &s.fulfiller,
+        &vec![&s.env, delivered, open, 99, with_code, also_open],
+    );
     assert_eq!(
-        words,
+        results,
         vec![
-            &env,
-            String::from_str(&env, "Hello"),
-            String::from_str(&env, "Dev"),
+            &s.env,
+            BatchResult::Err(OrderError::AlreadyFulfilled as u32),
+            BatchResult::Ok(open),
+            BatchResult::Err(OrderError::NotFound as u32),
+            BatchResult::Err(OrderError::InvalidDeliveryCode as u32),
+            BatchResult::Ok(also_open),
         ]
     );
+    assert_eq!(s.env.auths().len(), 1);
+    assert_eq!(s.env.auths()[0].0, s.fulfiller);
+    for order_id in [open, also_open] {
+        assert_eq!(s.client.get_order(&order_id).status, OrderStatus::Delivered);
+    }
+    assert_eq!(s.client.get_order(&with_code).status, OrderStatus::Created);
+
+    assert_eq!(
+        s.client
+            .try_fulfill_orders(&Address::generate(&s.env), &vec![&s.env, with_code]),
+        Err(Ok(OrderError::Unauthorized))
+    );
+    let mut too_many = Vec::new(&s.env);
+    for _ in 0..=MAX_BATCH_SIZE {
+        too_many.push_back(new_order(&s));
+    }
+    assert_eq!(
+        s.client.try_fulfill_orders(&s.fulfiller, &too_many),
+        Err(Ok(OrderError::BatchTooLarge))
+    );
+}
+
+// Per-transaction network limits on instructions, footprint entries (read and
+// written), written entries, written bytes and event bytes. The contract's Wasm
+// entry is missing from a native test's footprint, so one entry is held back
+const TX_LIMITS: [i64; 5] = [100_000_000, 40 - 1, 25, 132_096, 8_198];
+
+// Resources used by the last invocation, in the order of TX_LIMITS
+fn batch_usage(s: &Setup) -> [i64; 5] {
+    let resources = s.env.cost_estimate().resources();
+    [
+        resources.instructions,
+        (resources.read_entries + resources.write_entries) as i64,
+        resources.write_entries as i64,
+        resources.write_bytes as i64,
+        resources.contract_events_size_bytes as i64,
+    ]
+}
+
+// Largest batch fitting every limit, extrapolated from the cost of batches of one
+// and two entries (a batch grows linearly with its size)
+fn largest_batch(single: [i64; 5], double: [i64; 5]) -> i64 {
+    let mut largest = i64::MAX;
+    for i in 0..TX_LIMITS.len() {
+        let per_entry = double[i] - single[i];
+        if per_entry > 0 {
+            largest = largest.min(1 + (TX_LIMITS[i] - single[i]) / per_entry);
+        }
+    }
+    largest
+}
+
+#[test]
+fn test_batch_size_fits_network_limits() {
+    let s = setup();
+    let create = |size: usize| {
+        s.client.create_orders(
+            &s.buyer,
+            &s.seller,
+            &batch_products(&s, &std::vec![PRICE; size]),
+            &s.token,
+        );
+        batch_usage(&s)
+    };
+    let fulfill = |order_ids: &[u64]| {
+        s.client
+            .fulfill_orders(&s.fulfiller, &Vec::from_slice(&s.env, order_ids));
+        batch_usage(&s)
+    };
+
+    // Warm up the seller queue, so the first batch doesn't start it afresh
+    create(1);
+    let largest_create = largest_batch(create(1), create(2));
+    let largest_fulfill = largest_batch(fulfill(&[1]), fulfill(&[2, 3]));
+    assert_eq!(
+        MAX_BATCH_SIZE as i64,
+        largest_create.min(largest_fulfill),
+        "create {largest_create}, fulfill {largest_fulfill}"
+    );
+
+    // Full batches stay within every limit
+    let full = create(MAX_BATCH_SIZE as usize);
+    let ids: std::vec::Vec<u64> = (5..5 + MAX_BATCH_SIZE as u64).collect();
+    for usage in [full, fulfill(&ids)] {
+        for i in 0..TX_LIMITS.len() {
+            assert!(usage[i] <= TX_LIMITS[i], "{usage:?}");
+        }
+    }
 }